@@ -1,5 +1,7 @@
 use crate::manager::Wallpaper;
 use rand::Rng;
+use serde::Serialize;
+use std::path::Path;
 
 /// 壁纸选择器
 /// 实现二分切割和权重过滤算法，加入动态扰动
@@ -14,20 +16,62 @@ impl WallpaperSelector {
     /// 2. 按扰动后的权重排序
     /// 3. 找到前 tolerance 范围内的所有壁纸
     /// 4. 选择中间位置的壁纸（二分切割）
+    ///
+    /// `exclude` 指定的壁纸（通常是上一张）不参与选择，
+    /// 但壁纸库只有一张时仍允许重复播放
     pub fn select(
         wallpapers: &mut [Wallpaper],
         tolerance: f64,
         perturbation_ratio: f64,
+        exclude: Option<&Path>,
     ) -> Option<usize> {
+        let top_indices = Self::candidates(wallpapers, tolerance, perturbation_ratio, exclude);
+
+        // 二分切割：选择中间位置
+        let mid_index = top_indices.len() / 2;
+        top_indices.get(mid_index).copied()
+    }
+
+    /// 选择最多 n 张不同的壁纸
+    ///
+    /// 将候选梯队均分为 n 段，取每段的中间位置，使结果分散在整个梯队中；
+    /// n = 1 时与 `select` 的二分切割结果一致。n 超过梯队大小时返回整个梯队
+    #[allow(dead_code)]
+    pub fn select_n(
+        wallpapers: &[Wallpaper],
+        tolerance: f64,
+        perturbation_ratio: f64,
+        n: usize,
+    ) -> Vec<usize> {
+        let band = Self::candidates(wallpapers, tolerance, perturbation_ratio, None);
+        let n = n.min(band.len());
+
+        (0..n)
+            .map(|i| band[(2 * i + 1) * band.len() / (2 * n)])
+            .collect()
+    }
+
+    /// 获取候选壁纸（扰动后权重与最大值相差不超过 tolerance 的壁纸）
+    ///
+    /// 返回的索引按扰动后权重从高到低排列
+    pub fn candidates(
+        wallpapers: &[Wallpaper],
+        tolerance: f64,
+        perturbation_ratio: f64,
+        exclude: Option<&Path>,
+    ) -> Vec<usize> {
         if wallpapers.is_empty() {
-            return None;
+            return Vec::new();
         }
 
+        let exclude = exclude.filter(|_| wallpapers.len() > 1);
+
         // 应用动态扰动
         let mut rng = rand::thread_rng();
         let perturbed_values: Vec<(usize, f64)> = wallpapers
             .iter()
             .enumerate()
+            .filter(|(_, w)| exclude != Some(w.path.as_path()))
             .map(|(idx, w)| {
                 let random_factor = rng.gen_range(-1.0..1.0);
                 let perturbation = w.value * perturbation_ratio * random_factor;
@@ -40,22 +84,16 @@ impl WallpaperSelector {
         let mut sorted_indices = perturbed_values.clone();
         sorted_indices.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
-        let max_value = sorted_indices[0].1;
+        let Some(&(_, max_value)) = sorted_indices.first() else {
+            return Vec::new();
+        };
 
         // 找到前 tolerance 范围内的所有壁纸
-        let top_indices: Vec<usize> = sorted_indices
+        sorted_indices
             .iter()
             .filter(|(_, v)| (max_value - v).abs() <= tolerance)
             .map(|(idx, _)| *idx)
-            .collect();
-
-        if top_indices.is_empty() {
-            return Some(sorted_indices[0].0);
-        }
-
-        // 二分切割：选择中间位置
-        let mid_index = top_indices.len() / 2;
-        Some(top_indices[mid_index])
+            .collect()
     }
 
     pub fn get_stats(wallpapers: &[Wallpaper]) -> Stats {
@@ -67,23 +105,55 @@ impl WallpaperSelector {
         let sum: f64 = values.iter().sum();
         let count = values.len() as f64;
 
+        let avg_value = sum / count;
+        let variance = values.iter().map(|v| (v - avg_value).powi(2)).sum::<f64>() / count;
+
         Stats {
             count: wallpapers.len(),
             min_value: values.iter().cloned().fold(f64::INFINITY, f64::min),
             max_value: values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
-            avg_value: sum / count,
+            avg_value,
+            std_dev: variance.sqrt(),
+            fairness: Self::fairness_index(wallpapers),
             total_skips: wallpapers.iter().map(|w| w.skip_streak as u64).sum(),
         }
     }
+
+    /// 播放公平性指数：1 - 播放次数的基尼系数
+    ///
+    /// 1.0 表示每张壁纸播放次数完全相同，越接近 0 表示播放越集中在少数壁纸上；
+    /// 尚无播放记录时视为完全公平
+    pub fn fairness_index(wallpapers: &[Wallpaper]) -> f64 {
+        let mut counts: Vec<f64> = wallpapers.iter().map(|w| w.play_count as f64).collect();
+        let total: f64 = counts.iter().sum();
+        if counts.len() < 2 || total == 0.0 {
+            return 1.0;
+        }
+
+        counts.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let n = counts.len() as f64;
+        let weighted: f64 = counts
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (i + 1) as f64 * c)
+            .sum();
+        let gini = 2.0 * weighted / (n * total) - (n + 1.0) / n;
+
+        1.0 - gini
+    }
 }
 
 /// 壁纸库统计信息
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct Stats {
     pub count: usize,
     pub min_value: f64,
     pub max_value: f64,
     pub avg_value: f64,
+    /// 权重标准差
+    pub std_dev: f64,
+    /// 播放公平性指数（1 - 播放次数基尼系数）
+    pub fairness: f64,
     pub total_skips: u64,
 }
 
@@ -91,8 +161,110 @@ impl std::fmt::Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "壁纸数量: {}\n权重范围: {:.2} ~ {:.2}\n平均权重: {:.2}\n总跳过次数: {}",
-            self.count, self.min_value, self.max_value, self.avg_value, self.total_skips
+            "壁纸数量: {}\n权重范围: {:.2} ~ {:.2}\n平均权重: {:.2}\n权重标准差: {:.2}\n播放公平性: {:.2}\n总跳过次数: {}",
+            self.count,
+            self.min_value,
+            self.max_value,
+            self.avg_value,
+            self.std_dev,
+            self.fairness,
+            self.total_skips
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn wallpaper(path: &str, value: f64) -> Wallpaper {
+        Wallpaper {
+            path: PathBuf::from(path),
+            value,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_alternates_with_two_wallpapers() {
+        // 权重几乎相同，两张都落在容差范围内
+        let mut wallpapers = vec![wallpaper("/a.mp4", 100.0), wallpaper("/b.mp4", 99.0)];
+        let mut last: Option<PathBuf> = None;
+
+        for _ in 0..10 {
+            let idx =
+                WallpaperSelector::select(&mut wallpapers, 5.0, 0.03, last.as_deref()).unwrap();
+            let picked = wallpapers[idx].path.clone();
+            assert_ne!(Some(&picked), last.as_ref());
+            last = Some(picked);
+        }
+    }
+
+    #[test]
+    fn test_larger_tolerance_broadens_candidates() {
+        let wallpapers: Vec<Wallpaper> = (0..10)
+            .map(|i| wallpaper(&format!("/{}.mp4", i), 100.0 - i as f64 * 5.0))
+            .collect();
+
+        let narrow = WallpaperSelector::candidates(&wallpapers, 1.0, 0.0, None);
+        let wide = WallpaperSelector::candidates(&wallpapers, 50.0, 0.0, None);
+
+        assert_eq!(narrow.len(), 1);
+        assert!(wide.len() > narrow.len());
+        assert_eq!(wide.len(), 10);
+    }
+
+    #[test]
+    fn test_select_n_spreads_and_clamps() {
+        let wallpapers: Vec<Wallpaper> = (0..10)
+            .map(|i| wallpaper(&format!("/{}.mp4", i), 100.0 - i as f64))
+            .collect();
+
+        // 梯队为权重 100 ~ 96 的 5 张
+        let picked = WallpaperSelector::select_n(&wallpapers, 4.0, 0.0, 2);
+        assert_eq!(picked, vec![1, 3]);
+
+        let single = WallpaperSelector::select_n(&wallpapers, 4.0, 0.0, 1);
+        assert_eq!(single, vec![2]);
+
+        // n 超过梯队大小时只返回梯队内的壁纸
+        let all = WallpaperSelector::select_n(&wallpapers, 4.0, 0.0, 20);
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+
+        assert!(WallpaperSelector::select_n(&[], 4.0, 0.0, 3).is_empty());
+    }
+
+    #[test]
+    fn test_fairness_index_skewed_vs_uniform() {
+        let with_counts = |counts: &[u32]| -> Vec<Wallpaper> {
+            counts
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| Wallpaper {
+                    play_count: c,
+                    ..wallpaper(&format!("/{}.mp4", i), 100.0)
+                })
+                .collect()
+        };
+
+        let uniform = WallpaperSelector::fairness_index(&with_counts(&[5, 5, 5, 5]));
+        let skewed = WallpaperSelector::fairness_index(&with_counts(&[20, 0, 0, 0]));
+        let mild = WallpaperSelector::fairness_index(&with_counts(&[6, 5, 5, 4]));
+
+        assert!((uniform - 1.0).abs() < 1e-9);
+        assert!((skewed - 0.25).abs() < 1e-9);
+        assert!(skewed < mild && mild < uniform);
+        assert_eq!(
+            WallpaperSelector::fairness_index(&with_counts(&[0, 0])),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_select_single_wallpaper_repeats() {
+        let mut wallpapers = vec![wallpaper("/a.mp4", 100.0)];
+        let idx = WallpaperSelector::select(&mut wallpapers, 5.0, 0.03, Some(Path::new("/a.mp4")));
+        assert_eq!(idx, Some(0));
+    }
+}
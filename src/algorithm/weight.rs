@@ -1,6 +1,7 @@
 use crate::config::WeightConfig;
 use crate::manager::Wallpaper;
-use rand::Rng;
+use log::info;
+use rand::{Rng, RngCore};
 
 /// 权重计算器
 ///
@@ -12,6 +13,7 @@ use rand::Rng;
 pub struct WeightCalculator {
     config: WeightConfig,
     selection_count: u32, // 用于跟踪洗牌周期
+    rng: Box<dyn RngCore>,
 }
 
 impl WeightCalculator {
@@ -19,45 +21,91 @@ impl WeightCalculator {
         Self {
             config,
             selection_count: 0,
+            rng: Box::new(rand::thread_rng()),
         }
     }
 
+    /// 使用固定种子创建权重计算器，洗牌结果可复现（用于测试）
+    #[cfg(test)]
+    pub fn with_seed(config: WeightConfig, seed: u64) -> Self {
+        use rand::SeedableRng;
+
+        Self {
+            config,
+            selection_count: 0,
+            rng: Box::new(rand::rngs::StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// 按文件新旧计算初始权重：最新的文件（file_age_ratio = 0）为 base + initial_spread，
+    /// 最旧的文件（file_age_ratio = 1）为 base - initial_spread
     pub fn calculate_initial_weight(&self, file_age_ratio: f64) -> f64 {
-        let min_weight = self.config.base - 20.0;
-        let max_weight = self.config.base + 20.0;
+        let min_weight = self.config.base - self.config.initial_spread;
+        let max_weight = self.config.base + self.config.initial_spread;
 
         max_weight - (file_age_ratio * (max_weight - min_weight))
     }
 
+    /// 根据连续跳过次数计算奖励倍率
+    ///
+    /// 在梯度表中查找阈值 ≤ skip_streak 的最高一档，未配置梯度表时返回 1.0
+    pub fn calculate_skip_reward(&self, skip_streak: u32) -> f64 {
+        self.config
+            .skip_reward_tiers
+            .iter()
+            .filter(|(threshold, _)| *threshold <= skip_streak)
+            .max_by_key(|(threshold, _)| *threshold)
+            .map(|(_, multiplier)| multiplier.max(0.0))
+            .unwrap_or(1.0)
+    }
+
     /// 更新所有壁纸权重（零和博弈实现）
     ///
     /// 核心逻辑：
     /// - 选中壁纸减少 penalty
-    /// - 其他壁纸平均分配这个 penalty
+    /// - 其他壁纸按跳过奖励倍率分配这些 penalty（未配置梯度表时平均分配）
     /// - 总权重保持不变 ⇒ 零和博弈
-    pub fn update_weights_zero_sum(&mut self, wallpapers: &mut [Wallpaper], selected_index: usize) {
-        if wallpapers.is_empty() {
+    ///
+    /// 多显示器时一次选中多张壁纸，视为一轮选择：跳过次数、洗牌周期和归一化都只计算一次
+    pub fn update_weights_zero_sum(&mut self, wallpapers: &mut [Wallpaper], selected: &[usize]) {
+        let other_count = (0..wallpapers.len())
+            .filter(|idx| !selected.contains(idx))
+            .count();
+        if other_count == 0 || other_count == wallpapers.len() {
+            // 全部被选中（如只有一张壁纸）或没有有效的选中项，不需要调整
             return;
         }
 
         let penalty = self.config.select_penalty;
-        let other_count = wallpapers.len() - 1;
+        let total_penalty = penalty * (wallpapers.len() - other_count) as f64;
 
-        if other_count == 0 {
-            // 只有一张壁纸，不需要调整
-            return;
-        }
-
-        let reward_per_wallpaper = penalty / other_count as f64;
+        // 按跳过奖励倍率计算每张未选中壁纸的份额
+        let shares: Vec<f64> = wallpapers
+            .iter()
+            .enumerate()
+            .map(|(idx, w)| {
+                if selected.contains(&idx) {
+                    0.0
+                } else {
+                    self.calculate_skip_reward(w.skip_streak + 1)
+                }
+            })
+            .collect();
+        let total_share: f64 = shares.iter().sum();
 
         for (idx, wall) in wallpapers.iter_mut().enumerate() {
-            if idx == selected_index {
+            if selected.contains(&idx) {
                 // 选中壁纸：扣除惩罚
-                wall.value -= penalty;
+                wall.value = self.clamp_value(wall.value - penalty);
                 wall.skip_streak = 0;
             } else {
                 // 未选中壁纸：获得奖励
-                wall.value += reward_per_wallpaper;
+                let reward = if total_share > 0.0 {
+                    total_penalty * shares[idx] / total_share
+                } else {
+                    total_penalty / other_count as f64
+                };
+                wall.value = self.clamp_value(wall.value + reward);
                 wall.skip_streak += 1;
             }
         }
@@ -65,7 +113,10 @@ impl WeightCalculator {
         self.selection_count += 1;
 
         // 检查是否需要洗牌
-        if self.config.shuffle_period > 0 && self.selection_count % self.config.shuffle_period == 0
+        if self.config.shuffle_period > 0
+            && self
+                .selection_count
+                .is_multiple_of(self.config.shuffle_period)
         {
             self.apply_shuffle(wallpapers);
         }
@@ -74,6 +125,17 @@ impl WeightCalculator {
         self.auto_normalize(wallpapers);
     }
 
+    /// 将权重限制在配置的上下限内，未配置时原样返回
+    fn clamp_value(&self, value: f64) -> f64 {
+        let value = self
+            .config
+            .weight_floor
+            .map_or(value, |floor| value.max(floor));
+        self.config
+            .weight_ceiling
+            .map_or(value, |ceiling| value.min(ceiling))
+    }
+
     /// 自动归一化：当平均权重超过阈值时，将所有权重按比例缩放
     ///
     /// 目标：将平均权重调整为 normalization_target
@@ -95,7 +157,7 @@ impl WeightCalculator {
         if avg > self.config.normalization_threshold {
             let scale_factor = self.config.normalization_target / avg;
 
-            println!(
+            info!(
                 "🔄 自动归一化触发：平均权重 {:.2} → {:.2}（缩放系数 {:.4}）",
                 avg,
                 avg * scale_factor,
@@ -114,7 +176,7 @@ impl WeightCalculator {
     /// - 选择 shuffle_intensity 比例的壁纸
     /// - 将它们的权重重置为基础值附近的随机值
     /// - 打破固定的权重梯度，引入新的随机性
-    fn apply_shuffle(&self, wallpapers: &mut [Wallpaper]) {
+    fn apply_shuffle(&mut self, wallpapers: &mut [Wallpaper]) {
         if wallpapers.is_empty() || self.config.shuffle_intensity <= 0.0 {
             return;
         }
@@ -127,7 +189,7 @@ impl WeightCalculator {
             return;
         }
 
-        let mut rng = rand::thread_rng();
+        let rng = &mut self.rng;
         let mut indices: Vec<usize> = (0..wallpapers.len()).collect();
 
         // Fisher-Yates 洗牌
@@ -136,14 +198,13 @@ impl WeightCalculator {
             indices.swap(i, j);
         }
 
-        println!(
+        info!(
             "🎲 周期性洗牌：重置 {} 张壁纸权重（强度 {:.0}%）",
             shuffle_count,
             self.config.shuffle_intensity * 100.0
         );
 
-        for i in 0..shuffle_count {
-            let idx = indices[i];
+        for &idx in indices.iter().take(shuffle_count) {
             // 重置为基础权重附近的随机值（±20%）
             let random_offset = rng.gen_range(-0.2..0.2);
             wallpapers[idx].value = self.config.base * (1.0 + random_offset);
@@ -151,7 +212,247 @@ impl WeightCalculator {
         }
     }
 
+    /// 时间衰减：让壁纸权重随真实经过的时间向基础权重回归
+    ///
+    /// 经过时间从 last_played 与 last_decayed 中较晚者算起，避免重复衰减；
+    /// decay_per_hour 为 0 时不做任何处理
+    pub fn apply_decay(&self, wallpapers: &mut [Wallpaper], now: u64) {
+        let decay_per_hour = self.config.decay_per_hour;
+        if decay_per_hour <= 0.0 {
+            return;
+        }
+
+        let base = self.config.base;
+        for wall in wallpapers.iter_mut() {
+            let Some(since) = wall.last_played.max(wall.last_decayed) else {
+                continue;
+            };
+
+            let hours = now.saturating_sub(since) as f64 / 3600.0;
+            let delta = decay_per_hour * hours;
+            wall.value = if wall.value < base {
+                (wall.value + delta).min(base)
+            } else {
+                (wall.value - delta).max(base)
+            };
+            wall.last_decayed = Some(now);
+        }
+    }
+
     pub fn base_weight(&self) -> f64 {
         self.config.base
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::path::PathBuf;
+
+    fn wallpapers(count: usize) -> Vec<Wallpaper> {
+        (0..count)
+            .map(|i| Wallpaper {
+                path: PathBuf::from(format!("/{}.mp4", i)),
+                value: 100.0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn config() -> WeightConfig {
+        WeightConfig {
+            shuffle_period: 0,
+            ..Config::default().weight
+        }
+    }
+
+    #[test]
+    fn test_initial_weight_uses_configured_spread() {
+        let calc = WeightCalculator::new(config());
+        assert_eq!(calc.calculate_initial_weight(0.0), 120.0);
+        assert_eq!(calc.calculate_initial_weight(0.5), 100.0);
+        assert_eq!(calc.calculate_initial_weight(1.0), 80.0);
+
+        let calc = WeightCalculator::new(WeightConfig {
+            base: 1000.0,
+            initial_spread: 200.0,
+            ..config()
+        });
+        assert_eq!(calc.calculate_initial_weight(0.0), 1200.0);
+        assert_eq!(calc.calculate_initial_weight(1.0), 800.0);
+    }
+
+    #[test]
+    fn test_skip_reward_lookup() {
+        let calc = WeightCalculator::new(WeightConfig {
+            skip_reward_tiers: vec![(0, 1.0), (3, 2.0), (5, 4.0)],
+            ..config()
+        });
+        assert_eq!(calc.calculate_skip_reward(0), 1.0);
+        assert_eq!(calc.calculate_skip_reward(2), 1.0);
+        assert_eq!(calc.calculate_skip_reward(3), 2.0);
+        assert_eq!(calc.calculate_skip_reward(10), 4.0);
+
+        let default_calc = WeightCalculator::new(config());
+        assert_eq!(default_calc.calculate_skip_reward(10), 1.0);
+    }
+
+    #[test]
+    fn test_steep_tiers_grow_skipped_weight_faster() {
+        // 壁纸 0 一直不被选中，其余壁纸轮流被选中
+        let run = |calc: &mut WeightCalculator| {
+            let mut walls = wallpapers(4);
+            for i in 0..12 {
+                calc.update_weights_zero_sum(&mut walls, &[1 + i % 3]);
+            }
+            walls
+        };
+
+        let default_walls = run(&mut WeightCalculator::new(config()));
+        let steep_walls = run(&mut WeightCalculator::new(WeightConfig {
+            skip_reward_tiers: vec![(0, 1.0), (2, 4.0), (4, 16.0)],
+            ..config()
+        }));
+
+        assert!(steep_walls[0].value > default_walls[0].value);
+
+        // 总权重守恒
+        let total: f64 = steep_walls.iter().map(|w| w.value).sum();
+        assert!((total - 400.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_multiple_selection_is_one_round() {
+        let mut calc = WeightCalculator::new(config());
+        let mut walls = wallpapers(4);
+        calc.update_weights_zero_sum(&mut walls, &[0, 2]);
+
+        let values: Vec<f64> = walls.iter().map(|w| w.value).collect();
+        assert_eq!(values, vec![90.0, 110.0, 90.0, 110.0]);
+        let streaks: Vec<u32> = walls.iter().map(|w| w.skip_streak).collect();
+        assert_eq!(streaks, vec![0, 1, 0, 1]);
+        assert_eq!(calc.selection_count, 1);
+    }
+
+    #[test]
+    fn test_weight_bounds_saturate() {
+        let mut calc = WeightCalculator::new(WeightConfig {
+            weight_floor: Some(50.0),
+            weight_ceiling: Some(180.0),
+            normalization_threshold: f64::MAX,
+            ..config()
+        });
+        let mut walls = wallpapers(4);
+
+        // 壁纸 1 一直被选中，其余壁纸一直被跳过
+        for _ in 0..200 {
+            calc.update_weights_zero_sum(&mut walls, &[1]);
+        }
+        assert_eq!(walls[1].value, 50.0);
+        assert_eq!(walls[0].value, 180.0);
+
+        // 不设置上下限时权重会越过这些边界
+        let mut calc = WeightCalculator::new(WeightConfig {
+            normalization_threshold: f64::MAX,
+            ..config()
+        });
+        let mut walls = wallpapers(4);
+        for _ in 0..200 {
+            calc.update_weights_zero_sum(&mut walls, &[1]);
+        }
+        assert!(walls[1].value < 50.0);
+        assert!(walls[0].value > 180.0);
+    }
+
+    #[test]
+    fn test_decay_moves_toward_base() {
+        let calc = WeightCalculator::new(WeightConfig {
+            decay_per_hour: 5.0,
+            ..config()
+        });
+        let mut walls = wallpapers(3);
+        walls[0].value = 60.0;
+        walls[0].last_played = Some(0);
+        walls[1].value = 140.0;
+        walls[1].last_played = Some(0);
+        walls[2].value = 80.0;
+
+        // 两小时后：±10，未播放过的壁纸不变
+        calc.apply_decay(&mut walls, 7200);
+        assert!((walls[0].value - 70.0).abs() < 1e-9);
+        assert!((walls[1].value - 130.0).abs() < 1e-9);
+        assert_eq!(walls[2].value, 80.0);
+
+        // 再次加载时只计算新经过的时间，且不会越过基础权重
+        calc.apply_decay(&mut walls, 7200 + 3600 * 100);
+        assert_eq!(walls[0].value, 100.0);
+        assert_eq!(walls[1].value, 100.0);
+    }
+
+    #[test]
+    fn test_seeded_shuffle_is_reproducible() {
+        let config = WeightConfig {
+            shuffle_period: 5,
+            shuffle_intensity: 0.5,
+            ..Config::default().weight
+        };
+
+        let run = |seed: u64| {
+            let mut calc = WeightCalculator::with_seed(config.clone(), seed);
+            let mut walls = wallpapers(6);
+            for i in 0..50 {
+                calc.update_weights_zero_sum(&mut walls, &[i % 6]);
+            }
+            walls.iter().map(|w| w.value).collect::<Vec<f64>>()
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(7));
+    }
+
+    #[test]
+    fn test_seeded_long_run_keeps_play_counts_fair() {
+        use crate::algorithm::WallpaperSelector;
+
+        let config = WeightConfig {
+            shuffle_period: 20,
+            shuffle_intensity: 0.25,
+            ..Config::default().weight
+        };
+        // 不加扰动时选择结果只取决于权重，洗牌使用固定种子，整个过程可复现
+        let run = |seed: u64| {
+            let mut calc = WeightCalculator::with_seed(config.clone(), seed);
+            let mut walls = wallpapers(8);
+            walls[0].value = 160.0;
+            walls[7].value = 40.0;
+            let mut last: Option<PathBuf> = None;
+            for _ in 0..400 {
+                let idx = WallpaperSelector::select(&mut walls, 5.0, 0.0, last.as_deref()).unwrap();
+                walls[idx].play_count += 1;
+                last = Some(walls[idx].path.clone());
+                calc.update_weights_zero_sum(&mut walls, &[idx]);
+            }
+            walls
+        };
+
+        let walls = run(2024);
+        let counts: Vec<u32> = walls.iter().map(|w| w.play_count).collect();
+        assert_eq!(
+            counts,
+            run(2024).iter().map(|w| w.play_count).collect::<Vec<_>>()
+        );
+
+        // 初始权重差距很大，长期来看每张壁纸的播放次数仍接近平均值（50）
+        assert!(
+            counts.iter().all(|&c| (40..=60).contains(&c)),
+            "{:?}",
+            counts
+        );
+        assert!(
+            WallpaperSelector::fairness_index(&walls) > 0.9,
+            "{:?}",
+            counts
+        );
+    }
+}
@@ -1,4 +1,10 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+use crate::config::WallpaperMode;
+use crate::manager::{ListFormat, SortKey};
+use crate::paperengine::ENGINE_TYPES;
+use std::path::PathBuf;
 
 /// LianWall - 智能动态壁纸管理器
 /// 基于负反馈闭环调节的壁纸轮换系统
@@ -8,6 +14,22 @@ use clap::{Parser, Subcommand};
 #[command(version = "2.0.0")]
 #[command(about = "智能动态壁纸管理器", long_about = None)]
 pub struct Cli {
+    /// 指定配置文件路径（默认 ~/.config/lianwall/config.toml）
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// 输出更详细的日志（-v 为 debug，-vv 为 trace）
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// 仅输出警告和错误
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// 临时覆盖当前模式的壁纸引擎（不修改配置文件），用于对比测试
+    #[arg(long, global = true, value_parser = clap::builder::PossibleValuesParser::new(ENGINE_TYPES))]
+    pub engine: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -15,10 +37,34 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// 启动后台守护进程（动态壁纸模式）
-    Daemon,
+    Daemon {
+        /// 演练模式：只打印将要选择的壁纸，不实际切换也不修改权重
+        #[arg(long)]
+        dry_run: bool,
+
+        /// 只运行一个周期（切换一次后退出），适合 cron / systemd timer
+        #[arg(long)]
+        once: bool,
+    },
 
     /// 立即切换到下一张壁纸（根据当前模式）
-    Next,
+    Next {
+        /// 连续切换的次数（用于调试选择算法）
+        #[arg(long, default_value = "1")]
+        count: u32,
+
+        /// 演练模式：只打印将要选择的壁纸，不实际切换也不修改权重
+        #[arg(long)]
+        dry_run: bool,
+
+        /// 只在带有该标签的壁纸中选择
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// 只切换指定显示器（如 DP-1），其他显示器保持当前壁纸
+        #[arg(long)]
+        output: Option<String>,
+    },
 
     /// 切换到动态壁纸模式（视频）
     Video,
@@ -30,22 +76,186 @@ pub enum Commands {
     Reset {
         /// 指定模式: video 或 picture，默认 video
         #[arg(short, long, default_value = "video")]
-        mode: String,
+        mode: WallpaperMode,
+
+        /// 丢弃全部权重记录，所有壁纸按首次运行重新初始化
+        #[arg(long)]
+        hard: bool,
     },
 
     /// 显示当前状态和壁纸列表
     Status {
         /// 指定模式: video 或 picture，不指定则显示当前模式
         #[arg(short, long)]
-        mode: Option<String>,
+        mode: Option<WallpaperMode>,
+
+        /// 以 JSON 格式输出（便于 waybar/eww 等状态栏解析）
+        #[arg(long)]
+        json: bool,
+
+        /// 壁纸列表格式: table, csv, json（csv / json 只输出壁纸列表）
+        #[arg(long, value_enum, default_value_t = ListFormat::Table)]
+        format: ListFormat,
+
+        /// 只列出带有该标签的壁纸
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// 壁纸列表排序: value（权重）, skips（跳过次数）, name（文件名）, recent（最近播放）
+        #[arg(long, value_enum, default_value_t = SortKey::Value)]
+        sort: SortKey,
+    },
+
+    /// 显示长期播放次数统计（按播放次数排序）
+    Stats {
+        /// 指定模式: video 或 picture，不指定则显示当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
     },
 
     /// 停止所有壁纸引擎
     Kill,
+
+    /// 暂停守护进程的自动轮换（保留当前壁纸）
+    Pause,
+
+    /// 恢复守护进程的自动轮换
+    Resume,
+
+    /// 提高指定壁纸的权重，让它更快出现
+    Like {
+        /// 壁纸文件路径
+        path: String,
+    },
+
+    /// 降低指定壁纸的权重，让它更少出现
+    Dislike {
+        /// 壁纸文件路径
+        path: String,
+    },
+
+    /// 固定壁纸：守护进程停止轮换并保持显示该壁纸
+    Pin {
+        /// 壁纸文件路径，不指定则固定当前壁纸
+        path: Option<String>,
+    },
+
+    /// 取消固定壁纸，恢复自动轮换
+    Unpin,
+
+    /// 在运行时修改轮换使用的标签（覆盖配置文件中的 tags）
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// 输出当前显示的壁纸路径（便于脚本使用）
+    Current,
+
+    /// 输出配置文件原文与运行时解析后的实际配置（便于排查问题）
+    ShowConfig,
+
+    /// 用 $EDITOR 打开配置文件，保存后检查格式是否正确
+    Edit,
+
+    /// 导出权重缓存到文件（用于迁移或备份）
+    Export {
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+
+        /// 导出文件路径
+        out: String,
+    },
+
+    /// 从文件导入权重缓存，按路径与当前壁纸库合并
+    Import {
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+
+        /// 要导入的缓存文件
+        file: String,
+    },
+
+    /// 对照文件系统检查权重缓存：列出已删除或无法读取的记录，以及没有记录的文件
+    Verify {
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+
+        /// 从缓存中移除已删除或无法读取的记录
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// 查找内容相同的壁纸（文件大小与开头相同的副本），可标记副本使轮换只计一份
+    Dedup {
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+
+        /// 将副本标记为重复，轮换时不再选中（不删除文件）
+        #[arg(long)]
+        remove: bool,
+
+        /// 同时从磁盘删除副本文件（需与 --remove 一起使用，删除前逐字节确认内容相同）
+        #[arg(long, requires = "remove")]
+        delete_files: bool,
+
+        /// 清除所有重复标记
+        #[arg(long, conflicts_with = "remove")]
+        clear: bool,
+    },
+
+    /// 生成 shell 补全脚本，例如 `lianwall completions zsh > ~/.zfunc/_lianwall`
+    Completions {
+        /// 目标 shell: bash, zsh, fish, elvish, powershell
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagAction {
+    /// 添加标签到轮换范围
+    Add {
+        /// 标签名（可指定多个）
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+    },
+
+    /// 从轮换范围中移除标签
+    Remove {
+        /// 标签名（可指定多个）
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+    },
+
+    /// 清除运行时设置，恢复使用配置文件中的 tags
+    Clear {
+        /// 指定模式: video 或 picture，不指定则使用当前模式
+        #[arg(short, long)]
+        mode: Option<WallpaperMode>,
+    },
 }
 
 impl Cli {
     pub fn parse_args() -> Self {
         Cli::parse()
     }
+
+    /// 将指定 shell 的补全脚本输出到 stdout
+    pub fn print_completions(shell: Shell) {
+        let mut command = Cli::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+    }
 }
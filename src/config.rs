@@ -1,8 +1,37 @@
 #![allow(dead_code)]
 
+use chrono::Timelike;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 配置加载错误
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 读取或写入配置文件失败
+    Io(PathBuf, std::io::Error),
+    /// 配置文件格式错误
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => {
+                write!(f, "无法读写配置文件 {}: {}", path.display(), e)
+            }
+            ConfigError::Parse(path, e) => {
+                // toml 的错误信息已包含行号、列号以及出错的字段
+                write!(f, "配置文件格式错误 {}:\n{}", path.display(), e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
 
 /// 路径配置
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -15,6 +44,23 @@ pub struct PathsConfig {
     pub video_dir: String,
     /// 静态壁纸目录
     pub image_dir: String,
+    /// 排除规则（glob 模式，匹配的文件不会被扫描）
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// 扫描目录时是否跟随符号链接
+    #[serde(default = "default_follow_links")]
+    pub follow_links: bool,
+    /// 扫描目录时是否跳过隐藏文件和隐藏目录（以 . 开头）
+    #[serde(default = "default_skip_hidden")]
+    pub skip_hidden: bool,
+}
+
+fn default_follow_links() -> bool {
+    true
+}
+
+fn default_skip_hidden() -> bool {
+    true
 }
 
 /// 动态壁纸引擎配置
@@ -25,20 +71,119 @@ pub struct VideoEngineConfig {
     pub engine_type: String,
     /// 切换间隔（秒）
     pub interval: u64,
+    /// 切换间隔随机抖动范围（秒），0 表示固定间隔
+    #[serde(default)]
+    pub interval_jitter_secs: u64,
+    /// 显存剩余降级阈值（百分比），设置后覆盖 [vram] threshold_percent
+    #[serde(default)]
+    pub vram_low_threshold: Option<f32>,
+    /// 显存剩余恢复阈值（百分比），设置后覆盖 [vram] recovery_percent
+    #[serde(default)]
+    pub vram_recovery_threshold: Option<f32>,
+    /// 多显示器时为每个输出分别选择壁纸
+    #[serde(default)]
+    pub per_monitor: bool,
+    /// 守护进程监听壁纸目录，文件变化时自动重新扫描
+    #[serde(default)]
+    pub watch: bool,
+    /// 只在带有其中任一标签的壁纸中轮换，为空表示不限制
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 守护进程退出时停止引擎，不留下壁纸进程
+    #[serde(default)]
+    pub stop_on_exit: bool,
+    /// 距上次切换不足此时间（毫秒）时拒绝手动切换，0 表示不限制
+    #[serde(default = "default_min_switch_interval_ms")]
+    pub min_switch_interval_ms: u64,
+    /// 时长超过此值（秒）的视频不加入壁纸库，0 表示不限制
+    #[serde(default)]
+    pub max_video_seconds: u64,
+    /// 显存不足时显示的备用静态图片（不设置则切换到静态壁纸轮换）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_vram_fallback_image: Option<String>,
+    /// 传给 mpvpaper -o 的 mpv 参数
+    #[serde(default = "default_mpvpaper_options")]
+    pub mpvpaper_options: String,
+    /// mpv 硬件解码方式，"auto" 表示按显卡类型选择
+    #[serde(default = "default_hwdec")]
+    pub hwdec: String,
+    /// mpvpaper 的目标输出（"*" 表示所有显示器）
+    #[serde(default = "default_mpvpaper_output")]
+    pub mpvpaper_output: String,
+}
+
+fn default_min_switch_interval_ms() -> u64 {
+    500
+}
+
+fn default_mpvpaper_options() -> String {
+    crate::paperengine::mpvpaper::DEFAULT_OPTIONS.to_string()
+}
+fn default_mpvpaper_output() -> String {
+    "*".to_string()
+}
+fn default_hwdec() -> String {
+    "auto".to_string()
 }
 
 /// 静态壁纸引擎配置
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImageEngineConfig {
-    /// 引擎类型: "swww"
+    /// 引擎类型: "swww"、"swaybg" 或 "wpaperd"
     #[serde(rename = "type")]
     pub engine_type: String,
     /// 切换间隔（秒）
     pub interval: u64,
+    /// 切换间隔随机抖动范围（秒），0 表示固定间隔
+    #[serde(default)]
+    pub interval_jitter_secs: u64,
     /// 过渡效果
     pub transition: String,
     /// 过渡时长（秒）
     pub transition_duration: f32,
+    /// 过渡帧率
+    #[serde(default = "default_transition_fps")]
+    pub transition_fps: u32,
+    /// 过渡步长（越小越平滑）
+    #[serde(default = "default_transition_step")]
+    pub transition_step: u8,
+    /// 缩放模式: crop, fit, stretch, no
+    #[serde(default = "default_fill_mode")]
+    pub fill_mode: String,
+    /// 从动态壁纸切换过来时，等待新壁纸显示的最长时间（毫秒）
+    #[serde(default = "default_ready_timeout_ms")]
+    pub ready_timeout_ms: u64,
+    /// 过渡效果池，非空时每次切换随机选取其中一种
+    #[serde(default)]
+    pub transition_pool: Vec<String>,
+    /// 多显示器时为每个输出分别选择壁纸
+    #[serde(default)]
+    pub per_monitor: bool,
+    /// 守护进程监听壁纸目录，文件变化时自动重新扫描
+    #[serde(default)]
+    pub watch: bool,
+    /// 只在带有其中任一标签的壁纸中轮换，为空表示不限制
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 守护进程退出时停止引擎，不留下壁纸进程
+    #[serde(default)]
+    pub stop_on_exit: bool,
+    /// 距上次切换不足此时间（毫秒）时拒绝手动切换，0 表示不限制
+    #[serde(default = "default_min_switch_interval_ms")]
+    pub min_switch_interval_ms: u64,
+}
+
+fn default_transition_fps() -> u32 {
+    60
+}
+fn default_transition_step() -> u8 {
+    20
+}
+fn default_fill_mode() -> String {
+    "crop".to_string()
+}
+fn default_ready_timeout_ms() -> u64 {
+    3000
 }
 
 /// 权重配置
@@ -48,6 +193,9 @@ pub struct WeightConfig {
     pub base: f64,
     /// 选中惩罚值
     pub select_penalty: f64,
+    /// 初始权重的浮动范围（最新文件为 base + spread，最旧文件为 base - spread）
+    #[serde(default = "default_initial_spread")]
+    pub initial_spread: f64,
     /// 扰动幅度（相对百分比，如 0.03 表示 ±3%）
     #[serde(default = "default_perturbation_ratio")]
     pub perturbation_ratio: f64,
@@ -63,8 +211,65 @@ pub struct WeightConfig {
     /// 洗牌强度（每次洗牌重置的壁纸比例，0.0-1.0）
     #[serde(default = "default_shuffle_intensity")]
     pub shuffle_intensity: f64,
+    /// 选择容差（与最高权重相差不超过此值的壁纸视为同一梯队，
+    /// 增大此值会扩大候选池，选择更随机）
+    #[serde(default = "default_selection_tolerance")]
+    pub selection_tolerance: f64,
+    /// 选择策略（weighted 按权重选择，shuffle 纯随机，sequential 按路径顺序轮播）
+    #[serde(default)]
+    pub selection_strategy: SelectionStrategy,
+    /// 最短冷却时间（秒，播放后在此时间内不会再次被选中，0表示禁用）
+    #[serde(default)]
+    pub min_cooldown_secs: u64,
+    /// 同一次守护进程运行中最近 K 次播放过的壁纸不会再次被选中（只保存在内存中，0表示禁用）
+    #[serde(default)]
+    pub session_no_repeat: usize,
+    /// 跳过奖励梯度表（连续跳过次数阈值, 奖励倍率），为空时平均分配奖励
+    #[serde(default)]
+    pub skip_reward_tiers: Vec<(u32, f64)>,
+    /// 手动反馈（like/dislike）时增减的权重
+    #[serde(default = "default_feedback_delta")]
+    pub feedback_delta: f64,
+    /// 时间衰减速度（每小时向基础权重回归的数值，0表示禁用）
+    #[serde(default)]
+    pub decay_per_hour: f64,
+    /// 新加入的壁纸以高于平均的权重开始，在前几轮中优先出现
+    #[serde(default)]
+    pub prefer_new_files: bool,
+    /// 权重下限（可选，不设置则不限制）
+    #[serde(default)]
+    pub weight_floor: Option<f64>,
+    /// 权重上限（可选，不设置则不限制）
+    #[serde(default)]
+    pub weight_ceiling: Option<f64>,
+}
+
+/// 壁纸选择策略
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionStrategy {
+    /// 负反馈权重算法（默认）
+    #[default]
+    Weighted,
+    /// 忽略权重，等概率随机选择，不与上一张重复
+    Shuffle,
+    /// 按文件路径排序依次播放，到末尾后从头开始
+    Sequential,
+}
+
+impl SelectionStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SelectionStrategy::Weighted => "weighted",
+            SelectionStrategy::Shuffle => "shuffle",
+            SelectionStrategy::Sequential => "sequential",
+        }
+    }
 }
 
+fn default_initial_spread() -> f64 {
+    20.0
+}
 fn default_perturbation_ratio() -> f64 {
     0.03
 }
@@ -80,6 +285,12 @@ fn default_shuffle_period() -> u32 {
 fn default_shuffle_intensity() -> f64 {
     0.1
 }
+fn default_selection_tolerance() -> f64 {
+    5.0
+}
+fn default_feedback_delta() -> f64 {
+    20.0
+}
 
 /// 显存监控配置
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -96,6 +307,12 @@ pub struct VramConfig {
     /// 检测间隔（秒）
     #[serde(default = "default_check_interval")]
     pub check_interval: u64,
+    /// 每 GB 文件大小在选择时扣除的权重，0 表示禁用
+    #[serde(default)]
+    pub size_penalty_factor: f64,
+    /// 显存剩余低于此百分比时才按文件大小扣除权重
+    #[serde(default = "default_size_penalty_threshold")]
+    pub size_penalty_threshold: f32,
 }
 
 fn default_vram_enabled() -> bool {
@@ -114,6 +331,10 @@ fn default_check_interval() -> u64 {
     10
 }
 
+fn default_size_penalty_threshold() -> f32 {
+    50.0
+}
+
 impl Default for VramConfig {
     fn default() -> Self {
         Self {
@@ -121,10 +342,193 @@ impl Default for VramConfig {
             threshold_percent: default_threshold_percent(),
             recovery_percent: default_recovery_percent(),
             check_interval: default_check_interval(),
+            size_penalty_factor: 0.0,
+            size_penalty_threshold: default_size_penalty_threshold(),
         }
     }
 }
 
+/// 静态壁纸预缩放配置
+///
+/// 启用后，超过屏幕分辨率的图片会先缩放到刚好覆盖屏幕的尺寸并缓存，
+/// 之后的切换直接使用缓存，减少 swww 加载大图的时间和内存
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageOptimizationConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 缩放后图片的缓存目录
+    #[serde(default = "default_resized_cache_dir")]
+    pub cache_dir: String,
+    /// 缓存所在磁盘剩余空间低于此值（MB）时不再生成缩放图片，直接使用原图
+    #[serde(default = "default_min_free_mb")]
+    pub min_free_mb: u64,
+}
+
+fn default_resized_cache_dir() -> String {
+    default_cache_file("resized")
+}
+
+fn default_min_free_mb() -> u64 {
+    500
+}
+
+impl Default for ImageOptimizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cache_dir: default_resized_cache_dir(),
+            min_free_mb: default_min_free_mb(),
+        }
+    }
+}
+
+/// 钩子命令配置
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct HooksConfig {
+    /// 切换壁纸成功后执行的命令，{path} / {name} / {weight} 会被替换
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_switch_command: Option<String>,
+}
+
+/// 配色提取配置（需要使用 palette feature 编译）
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaletteConfig {
+    /// 切换壁纸后提取主色调并写入配色文件
+    #[serde(default)]
+    pub enabled: bool,
+    /// 提取的颜色数量
+    #[serde(default = "default_palette_colors")]
+    pub colors: usize,
+    /// 配色文件路径（JSON）
+    #[serde(default = "default_palette_output")]
+    pub output: String,
+    /// 写入配色文件后执行的命令
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_extract_command: Option<String>,
+}
+
+fn default_palette_colors() -> usize {
+    8
+}
+
+fn default_palette_output() -> String {
+    default_cache_file("palette.json")
+}
+
+impl Default for PaletteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            colors: default_palette_colors(),
+            output: default_palette_output(),
+            on_extract_command: None,
+        }
+    }
+}
+
+/// 按时间段切换壁纸目录的配置
+///
+/// 未配置任何目录时不生效，壁纸目录始终使用 [paths] 中的设置
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleConfig {
+    /// 白天开始时间（HH:MM）
+    #[serde(default = "default_day_start")]
+    pub day_start: String,
+    /// 夜晚开始时间（HH:MM）
+    #[serde(default = "default_night_start")]
+    pub night_start: String,
+    /// 白天使用的动态壁纸目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_day_dir: Option<String>,
+    /// 夜晚使用的动态壁纸目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_night_dir: Option<String>,
+    /// 白天使用的静态壁纸目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_day_dir: Option<String>,
+    /// 夜晚使用的静态壁纸目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_night_dir: Option<String>,
+}
+
+fn default_day_start() -> String {
+    "06:00".to_string()
+}
+
+fn default_night_start() -> String {
+    "18:00".to_string()
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            day_start: default_day_start(),
+            night_start: default_night_start(),
+            video_day_dir: None,
+            video_night_dir: None,
+            image_day_dir: None,
+            image_night_dir: None,
+        }
+    }
+}
+
+impl ScheduleConfig {
+    /// 获取指定模式在给定时刻（一天中的第几分钟）应使用的目录，未配置时返回 None
+    pub fn dir_at(&self, mode: WallpaperMode, minute_of_day: u32) -> Option<&str> {
+        let (day_dir, night_dir) = match mode {
+            WallpaperMode::Video => (&self.video_day_dir, &self.video_night_dir),
+            WallpaperMode::Image => (&self.image_day_dir, &self.image_night_dir),
+        };
+        if self.is_day(minute_of_day) {
+            day_dir.as_deref()
+        } else {
+            night_dir.as_deref()
+        }
+    }
+
+    /// 判断给定时刻是否属于白天（支持跨午夜的时间段）
+    pub fn is_day(&self, minute_of_day: u32) -> bool {
+        let day_start = parse_hhmm(&self.day_start).unwrap_or_else(|| {
+            log::warn!("无效的 day_start \"{}\"，使用 06:00", self.day_start);
+            6 * 60
+        });
+        let night_start = parse_hhmm(&self.night_start).unwrap_or_else(|| {
+            log::warn!("无效的 night_start \"{}\"，使用 18:00", self.night_start);
+            18 * 60
+        });
+
+        if day_start <= night_start {
+            (day_start..night_start).contains(&minute_of_day)
+        } else {
+            minute_of_day >= day_start || minute_of_day < night_start
+        }
+    }
+}
+
+/// 当前 Unix 时间（毫秒）
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 距上次切换（last，Unix 毫秒）不足 min 时返回还需等待的时间
+///
+/// 系统时间被调早导致 last 晚于 now 时按刚刚切换处理，最多等待一个 min
+fn switch_wait(last: Option<u64>, now: u64, min: Duration) -> Option<Duration> {
+    let elapsed = Duration::from_millis(now.saturating_sub(last?));
+    (elapsed < min).then(|| min - elapsed)
+}
+
+/// 解析 HH:MM 格式的时间，返回一天中的第几分钟
+fn parse_hhmm(s: &str) -> Option<u32> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
 /// 总配置结构
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
@@ -134,8 +538,22 @@ pub struct Config {
     pub weight: WeightConfig,
     #[serde(default)]
     pub vram: VramConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default)]
+    pub image_optimization: ImageOptimizationConfig,
+    /// 按扩展名指定显示引擎（扩展名 → 引擎类型），覆盖所在模式的默认引擎
+    #[serde(default)]
+    pub extension_engine: BTreeMap<String, String>,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub palette: PaletteConfig,
     #[serde(skip)]
     pub current_mode: Option<String>,
+    /// 配置文件路径（通过 --config 指定时覆盖默认位置）
+    #[serde(skip)]
+    pub source_path: Option<PathBuf>,
 }
 
 /// 壁纸模式
@@ -145,36 +563,108 @@ pub enum WallpaperMode {
     Image,
 }
 
+impl std::str::FromStr for WallpaperMode {
+    type Err = String;
+
+    /// 解析命令行中的模式名（不区分大小写），picture / static 是 image 的别名
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "video" | "dynamic" => Ok(WallpaperMode::Video),
+            "image" | "picture" | "static" => Ok(WallpaperMode::Image),
+            _ => Err(format!(
+                "无效的模式 \"{}\"，可选值: video（dynamic）、image（picture、static）",
+                s
+            )),
+        }
+    }
+}
+
+/// 默认缓存文件路径，遵循 XDG_CACHE_HOME（通过 dirs::cache_dir）
+fn default_cache_file(name: &str) -> String {
+    match dirs::cache_dir() {
+        Some(dir) => dir
+            .join("lianwall")
+            .join(name)
+            .to_string_lossy()
+            .into_owned(),
+        None => format!("~/.cache/lianwall/{}", name),
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             paths: PathsConfig {
-                video_cache: "~/.cache/lianwall/video.json".to_string(),
-                image_cache: "~/.cache/lianwall/image.json".to_string(),
+                video_cache: default_cache_file("video.json"),
+                image_cache: default_cache_file("image.json"),
                 video_dir: "~/Videos/background".to_string(),
                 image_dir: "~/Pictures/wallpapers".to_string(),
+                exclude: Vec::new(),
+                follow_links: default_follow_links(),
+                skip_hidden: default_skip_hidden(),
             },
             video_engine: VideoEngineConfig {
                 engine_type: "mpvpaper".to_string(),
                 interval: 600,
+                interval_jitter_secs: 0,
+                vram_low_threshold: None,
+                vram_recovery_threshold: None,
+                per_monitor: false,
+                watch: false,
+                tags: Vec::new(),
+                stop_on_exit: false,
+                min_switch_interval_ms: default_min_switch_interval_ms(),
+                max_video_seconds: 0,
+                low_vram_fallback_image: None,
+                mpvpaper_options: default_mpvpaper_options(),
+                hwdec: default_hwdec(),
+                mpvpaper_output: default_mpvpaper_output(),
             },
             image_engine: ImageEngineConfig {
                 engine_type: "swww".to_string(),
                 interval: 300,
+                interval_jitter_secs: 0,
                 transition: "fade".to_string(),
                 transition_duration: 2.0,
+                transition_fps: default_transition_fps(),
+                transition_step: default_transition_step(),
+                fill_mode: default_fill_mode(),
+                ready_timeout_ms: default_ready_timeout_ms(),
+                transition_pool: Vec::new(),
+                per_monitor: false,
+                watch: false,
+                tags: Vec::new(),
+                stop_on_exit: false,
+                min_switch_interval_ms: default_min_switch_interval_ms(),
             },
             weight: WeightConfig {
                 base: 100.0,
                 select_penalty: 10.0,
+                initial_spread: default_initial_spread(),
                 perturbation_ratio: 0.03,
                 normalization_threshold: 500.0,
                 normalization_target: 100.0,
                 shuffle_period: 100,
                 shuffle_intensity: 0.1,
+                selection_tolerance: 5.0,
+                selection_strategy: SelectionStrategy::default(),
+                min_cooldown_secs: 0,
+                session_no_repeat: 0,
+                skip_reward_tiers: Vec::new(),
+                feedback_delta: 20.0,
+                decay_per_hour: 0.0,
+                prefer_new_files: false,
+                weight_floor: None,
+                weight_ceiling: None,
             },
             vram: VramConfig::default(),
+            schedule: ScheduleConfig::default(),
+            image_optimization: ImageOptimizationConfig::default(),
+            extension_engine: BTreeMap::new(),
+            hooks: HooksConfig::default(),
+            palette: PaletteConfig::default(),
             current_mode: None,
+            source_path: None,
         }
     }
 }
@@ -186,28 +676,64 @@ impl Config {
             .join("lianwall/config.toml")
     }
 
-    pub fn load() -> Self {
-        let config_path = Self::config_path();
+    /// 加载配置文件
+    ///
+    /// 文件不存在时写入默认配置；文件存在但无法读取或解析时返回错误，
+    /// 不会覆盖用户的原有文件
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(&Self::config_path())
+    }
 
-        if config_path.exists() {
-            let content = fs::read_to_string(&config_path).expect("无法读取配置文件");
-            toml::from_str(&content).expect("配置文件格式错误")
+    /// 从指定路径加载配置文件，之后的 save 也会写回该路径
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let mut config = if path.exists() {
+            let content =
+                fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+            Self::parse(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?
         } else {
-            let config = Config::default();
-            config.save();
+            let config = Config {
+                source_path: Some(path.to_path_buf()),
+                ..Config::default()
+            };
             config
-        }
+                .save()
+                .map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+            config
+        };
+
+        config.source_path = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// 加载配置文件，失败时打印错误并回退到默认配置
+    ///
+    /// `path` 为 None 时使用默认配置路径
+    pub fn load_or_default(path: Option<&Path>) -> Self {
+        let path = path
+            .map(Path::to_path_buf)
+            .unwrap_or_else(Self::config_path);
+
+        Self::load_from(&path).unwrap_or_else(|e| {
+            log::error!("{}", e);
+            log::warn!("使用默认配置运行（原配置文件未被修改）");
+            Config::default()
+        })
+    }
+
+    /// 从 TOML 字符串解析配置
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
     }
 
-    pub fn save(&self) {
-        let config_path = Self::config_path();
+    /// 写入带注释的配置文件，目录只读或磁盘已满时返回错误
+    pub fn save(&self) -> std::io::Result<()> {
+        let config_path = self.source_path.clone().unwrap_or_else(Self::config_path);
 
         if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).ok();
+            fs::create_dir_all(parent)?;
         }
 
-        let content = self.to_toml_with_comments();
-        fs::write(&config_path, content).expect("无法写入配置文件");
+        fs::write(&config_path, self.to_toml_with_comments())
     }
 
     /// 生成带注释的 TOML 配置
@@ -222,6 +748,7 @@ impl Config {
 [paths]
 # 动态壁纸权重缓存文件路径
 #     用于保存视频壁纸的权重状态，支持持久化记忆
+#     默认位于 $XDG_CACHE_HOME/lianwall（未设置时为 ~/.cache/lianwall）
 video_cache = "{}"
 
 # 静态壁纸权重缓存文件路径
@@ -236,6 +763,23 @@ video_dir = "{}"
 #     存放图片壁纸文件的目录（支持 jpg, png, gif 等格式）
 image_dir = "{}"
 
+# 排除规则
+#     glob 模式列表，匹配的文件不会被加入壁纸库，并会从权重缓存中移除
+#     支持文件名模式（如 "*.tmp"）和子目录模式（如 "**/private/*"）
+exclude = {}
+
+# 跟随符号链接
+#     扫描壁纸目录时是否进入符号链接指向的文件和目录
+#     若链接指向可能离线的网络存储（NFS / SMB），可设为 false 避免扫描卡住
+#     默认 true
+follow_links = {}
+
+# 跳过隐藏文件
+#     扫描时跳过以 . 开头的文件和目录（如 .git、.thumbnails 中的缩略图缓存）
+#     壁纸确实存放在隐藏目录中时可设为 false
+#     默认 true
+skip_hidden = {}
+
 # === 动态壁纸引擎配置 ===
 # 控制视频壁纸的播放行为
 [video_engine]
@@ -247,10 +791,76 @@ type = "{}"
 #     默认 600 秒（10 分钟）
 interval = {}
 
+# 切换间隔随机抖动（秒）
+#     每次切换后，下一次的间隔在 interval ± 此值之间随机选取，让切换不那么机械
+#     设为 0 表示固定间隔（默认）
+interval_jitter_secs = {}
+
+# 显存阈值覆盖（百分比，可选）
+#     设置后覆盖 [vram] 中的 threshold_percent / recovery_percent
+#     显存剩余低于 vram_low_threshold 时暂停动态壁纸，高于 vram_recovery_threshold 时恢复
+{}
+{}
+
+# 多显示器独立壁纸
+#     启用后每个显示器分别选择不同的壁纸（通过 hyprctl 获取显示器列表）
+#     默认 false（所有显示器显示同一张）
+per_monitor = {}
+
+# 监听壁纸目录
+#     启用后守护进程监听动态壁纸目录，新增 / 删除 / 修改文件时自动重新扫描
+#     无需手动执行 lianwall reset，默认 false
+watch = {}
+
+# 标签过滤
+#     只在带有其中任一标签的壁纸中轮换（标签来自壁纸旁的 sidecar 文件）
+#     例如 ["nature", "minimal"]；留空 [] 表示不限制（默认）
+#     可用 lianwall tag add / remove 在运行时修改
+tags = {}
+
+# 退出时停止引擎
+#     守护进程收到 SIGTERM / SIGINT（systemctl stop、Ctrl-C）退出时停止 mpvpaper
+#     默认 false（保留最后一张壁纸）
+stop_on_exit = {}
+
+# 最短切换间隔（毫秒）
+#     距上次切换不足此时间时拒绝 next 和 pin，避免快捷键连按时 mpvpaper 在启动途中被重启
+#     设为 0 表示不限制，默认 500
+min_switch_interval_ms = {}
+
+# 最长视频时长（秒）
+#     时长超过此值的视频（如误放进壁纸目录的电影）不加入壁纸库，只在日志中提示
+#     时长通过 ffprobe 读取并记录在权重缓存中，之后扫描不再重复读取；未安装 ffprobe 时不过滤
+#     默认 0（不限制）
+max_video_seconds = {}
+
+# 显存不足时的备用图片（可选）
+#     设置后，显存紧张时停止动态壁纸并用静态壁纸引擎显示这张图片，
+#     显存恢复后继续动态壁纸轮换；不设置则切换到静态壁纸目录轮换
+{}
+
+# mpvpaper 参数
+#     传给 mpv 的参数，例如需要声音时可去掉 --no-audio，或加上 --mute=yes
+#     不能为空，为空时使用默认值
+mpvpaper_options = "{}"
+
+# 硬件解码
+#     auto：按显卡类型选择（NVIDIA → nvdec，AMD / Intel → vaapi，未检测到显卡 → no）
+#     也可以写明 mpv 的 --hwdec 取值，如 "vaapi"、"nvdec-copy"、"no"，会覆盖 mpvpaper_options 中的 --hwdec
+#     双显卡笔记本上 auto 选错解码器导致画面撕裂时可手动指定
+hwdec = "{}"
+
+# mpvpaper 目标输出
+#     "*" 表示所有显示器，也可以指定显示器名称，如 "DP-1"
+mpvpaper_output = "{}"
+
 # === 静态壁纸引擎配置 ===
 # 控制图片壁纸的切换和过渡效果
 [image_engine]
-# 引擎类型：当前仅支持 "swww"
+# 引擎类型：支持 "swww"、"swaybg" 和 "wpaperd"
+#     swaybg 不支持过渡效果，transition 相关配置将被忽略
+#     wpaperd 通过改写 ~/.config/wpaperd/config.toml 中的 path 切换壁纸（其他配置项、注释和格式原样保留），
+#     过渡和缩放使用 wpaperd 自己的配置
 type = "{}"
 
 # 切换间隔（秒）
@@ -258,6 +868,11 @@ type = "{}"
 #     默认 300 秒（5 分钟）
 interval = {}
 
+# 切换间隔随机抖动（秒）
+#     每次切换后，下一次的间隔在 interval ± 此值之间随机选取
+#     设为 0 表示固定间隔（默认）
+interval_jitter_secs = {}
+
 # 过渡效果
 #     可选值：fade, left, right, top, bottom, wipe, wave, grow, center, any, outer, random
 transition = "{}"
@@ -266,6 +881,54 @@ transition = "{}"
 #     切换壁纸时的动画持续时间
 transition_duration = {}
 
+# 过渡效果池
+#     非空时每次切换从列表中随机选取一种过渡效果，忽略上面的 transition
+#     例如 ["wipe", "wave", "grow", "fade"]
+#     留空 [] 表示始终使用 transition（默认）
+transition_pool = {}
+
+# 过渡帧率
+#     过渡动画的帧率，建议与显示器刷新率一致，默认 60
+transition_fps = {}
+
+# 过渡步长（1-255）
+#     每帧颜色变化的幅度，越小越平滑，默认 20
+transition_step = {}
+
+# 缩放模式
+#     crop：等比缩放并裁剪填满屏幕（默认）
+#     fit：等比缩放完整显示，可能留边
+#     stretch：拉伸填满屏幕，不保持比例
+#     no：不缩放，居中显示原图
+fill_mode = "{}"
+
+# 显示确认超时（毫秒）
+#     从动态壁纸切换到静态壁纸时，先轮询 swww query 确认新图片已显示再停止 mpvpaper，
+#     避免旧视频闪现；超过此时间仍未确认时直接停止 mpvpaper
+#     swaybg 无法查询显示状态，固定等待 1 秒（不超过此值）
+#     默认 3000
+ready_timeout_ms = {}
+
+# 多显示器独立壁纸
+#     启用后每个显示器分别选择不同的图片
+per_monitor = {}
+
+# 监听壁纸目录
+#     启用后守护进程监听静态壁纸目录，文件变化时自动重新扫描，默认 false
+watch = {}
+
+# 标签过滤
+#     只在带有其中任一标签的图片中轮换，留空 [] 表示不限制（默认）
+tags = {}
+
+# 退出时停止引擎
+#     守护进程在静态壁纸模式下退出时停止 swww / swaybg，默认 false
+stop_on_exit = {}
+
+# 最短切换间隔（毫秒）
+#     距上次切换不足此时间时拒绝 next 和 pin，设为 0 表示不限制，默认 500
+min_switch_interval_ms = {}
+
 # === 权重算法配置 ===
 # 控制智能选择算法的行为（零和博弈机制）
 [weight]
@@ -280,6 +943,12 @@ base = {}
 #     默认 10.0
 select_penalty = {}
 
+# 初始权重浮动范围
+#     新壁纸按加入时间在 [base - 此值, base + 此值] 之间分配初始权重，最新的文件最高
+#     修改 base 时建议按比例调整，例如 base = 1000.0 时设为 200.0
+#     默认 20.0
+initial_spread = {}
+
 # 扰动幅度比例
 #     选择时应用的随机扰动强度（相对于当前权重的百分比）
 #     0.03 表示 ±3% 的随机波动
@@ -315,11 +984,71 @@ shuffle_period = {}
 #     建议范围：0.05 - 0.20
 shuffle_intensity = {}
 
+# 选择容差
+#     与最高权重相差不超过此值的壁纸被视为同一梯队，从中二分选取
+#     增大此值 → 候选池更大，选择更随机
+#     减小此值 → 更严格地选择权重最高的壁纸
+#     默认 5.0
+selection_tolerance = {}
+
+# 选择策略
+#     "weighted"   - 负反馈权重算法，播放越多越少出现（默认）
+#     "shuffle"    - 忽略权重，等概率随机选择，不与上一张重复
+#     "sequential" - 按文件路径排序依次播放，到末尾后从头开始（播放位置保存在缓存中，重启后继续）
+#     shuffle / sequential 下冷却、最近播放等限制仍然有效，权重照常记录
+selection_strategy = "{}"
+
+# 最短冷却时间（秒）
+#     壁纸播放后，在此时间内不会再次被选中（即使权重已恢复）
+#     若所有壁纸都在冷却中，则忽略冷却限制
+#     设为 0 表示禁用，例如 3600 表示一小时内不重复
+min_cooldown_secs = {}
+
+# 本次运行内不重复的次数
+#     守护进程记住最近 K 次播放的壁纸，选择时排除它们（与权重无关，不写入缓存）
+#     若排除后没有候选壁纸，则忽略此限制
+#     设为 0 表示禁用，例如 5 表示最近 5 次切换内不会重复
+session_no_repeat = {}
+
+# 跳过奖励梯度表
+#     格式：[[连续跳过次数阈值, 奖励倍率], ...]
+#     未选中的壁纸按倍率分享选中壁纸的惩罚（总权重仍然守恒）
+#     倍率取阈值 ≤ 当前连续跳过次数的最高一档
+#     例如 [[0, 1.0], [3, 2.0], [5, 4.0]] 表示连续跳过 5 次以上的壁纸获得 4 倍份额
+#     留空 [] 表示所有未选中壁纸平均分配
+skip_reward_tiers = {}
+
+# 手动反馈幅度
+#     执行 lianwall like / dislike 时，对指定壁纸增加 / 减少的权重
+#     增大此值 → 点赞的壁纸更快出现，点踩的壁纸更少出现
+#     默认 20.0
+feedback_delta = {}
+
+# 时间衰减速度
+#     每次加载壁纸库时，根据距上次播放的真实时间，让权重向基础权重回归
+#     例如 2.0 表示每小时回归 2 点，守护进程关闭期间同样生效
+#     设为 0 表示禁用（默认）
+decay_per_hour = {}
+
+# 优先播放新壁纸
+#     启用后，热重载或目录监听发现的新壁纸初始权重至少比平均权重高 3 次选中惩罚，
+#     在接下来的几轮中优先出现；首次扫描（没有权重缓存）时不生效
+#     新壁纸的加入时间优先取文件创建时间，文件系统不支持时使用修改时间
+#     默认 false
+prefer_new_files = {}
+
+# 权重上下限（可选）
+#     每次更新权重后将权重限制在 [weight_floor, weight_ceiling] 范围内
+#     防止长期运行后常被跳过的壁纸权重无限增长、常被选中的壁纸权重过低
+#     限制后总权重不再严格守恒；不设置则不限制（默认）
+{}
+{}
+
 # ================================================
 # === 显存监控配置 ===
 # ================================================
 # 当显存不足时自动切换到静态壁纸模式，释放显存给其他应用
-# 当前仅强支持 NVIDIA 显卡，AMD 显卡基本支持
+# 当前仅强支持 NVIDIA 显卡，AMD 显卡基本支持，Intel 显卡通过 sysfs 支持
 [vram]
 # 是否启用显存监控
 #     启用后，LianWall 会定期检测显存使用情况
@@ -347,39 +1076,260 @@ recovery_percent = {}
 #     建议范围：5-30 秒
 #     默认 10
 check_interval = {}
+
+# 按文件大小降低候选权重
+#     显存剩余低于 size_penalty_threshold 时，选择壁纸时每 GB 文件大小扣除 size_penalty_factor 点权重，
+#     在降级到静态壁纸之前优先选择较小的视频；只影响本次选择，不写入权重缓存
+#     例如 5.0 表示 4GB 的 4K 视频比 1GB 的视频少 15 点权重
+#     默认 0（禁用）
+size_penalty_factor = {}
+
+# 按文件大小降低权重的显存阈值（百分比）
+#     显存剩余高于此值时不做调整，默认 50
+size_penalty_threshold = {}
+
+# ================================================
+# === 时间段壁纸目录 ===
+# ================================================
+# 按本地时间在白天 / 夜晚使用不同的壁纸目录
+# 守护进程每个周期检查一次，时间段变化时重新扫描目录并立即切换壁纸
+# 某个模式未配置对应时间段的目录时，继续使用 [paths] 中的目录
+# 全部留空时不启用
+[schedule]
+# 白天开始时间（HH:MM），默认 06:00
+day_start = "{}"
+
+# 夜晚开始时间（HH:MM），默认 18:00
+#     早于 day_start 时表示白天跨越午夜
+night_start = "{}"
+
+# 白天 / 夜晚的动态壁纸目录
+{}
+{}
+
+# 白天 / 夜晚的静态壁纸目录
+{}
+{}
+
+# ================================================
+# === 静态壁纸预缩放 ===
+# ================================================
+# 超过屏幕分辨率的图片先缩放到刚好覆盖屏幕的尺寸并缓存，之后直接使用缓存
+# 屏幕分辨率通过 hyprctl / wlr-randr / swaymsg 检测，多显示器时取最大值
+# 检测失败、图片未超过屏幕分辨率或 GIF 时使用原图
+[image_optimization]
+# 是否启用，默认 false
+enabled = {}
+
+# 缩放后图片的缓存目录
+cache_dir = "{}"
+
+# 最小剩余空间（MB）
+#     缓存所在磁盘剩余空间低于此值时不再生成缩放图片，直接使用原图
+#     默认 500，设为 0 表示不检查
+min_free_mb = {}
+
+# ================================================
+# === 按扩展名选择引擎 ===
+# ================================================
+# 指定扩展名的文件改用其他引擎显示，覆盖所在模式的默认引擎（per_monitor 模式下不生效）
+# 引擎类型：mpvpaper, swww, swaybg, wpaperd；映射的扩展名在两种模式的壁纸目录中都会被扫描
+# 例如让静态壁纸目录中的 APNG / 动画 WebP 由 mpvpaper 播放：
+#     apng = "mpvpaper"
+#     webp = "mpvpaper"
+[extension_engine]
+{}
+
+# ================================================
+# === 钩子命令 ===
+# ================================================
+[hooks]
+# 切换壁纸后执行的命令（可选）
+#     每次成功切换后通过 sh -c 在后台执行，失败只记录日志，不影响切换
+#     {{path}}：壁纸完整路径，{{name}}：文件名，{{weight}}：当前权重
+#     例如 on_switch_command = 'notify-send "Wallpaper" "{{name}}"'
+{}
+
+# ================================================
+# === 配色提取 ===
+# ================================================
+[palette]
+# 切换壁纸后提取主色调并写入配色文件（供 pywal 风格的主题脚本使用）
+#     需要使用 cargo build --features palette 编译；动态壁纸先用 ffmpeg 截取一帧
+#     默认 false
+enabled = {}
+
+# 提取的颜色数量，按占比从高到低排列
+colors = {}
+
+# 配色文件路径（JSON）
+output = "{}"
+
+# 写入配色文件后执行的命令（可选）
+#     通过 sh -c 执行，环境变量 LIANWALL_PALETTE 为配色文件路径，LIANWALL_PATH 为壁纸路径
+#     例如 on_extract_command = "~/.local/bin/apply-theme.sh"
+{}
 "#,
             self.paths.video_cache,
             self.paths.image_cache,
             self.paths.video_dir,
             self.paths.image_dir,
+            toml_inline(&self.paths.exclude),
+            self.paths.follow_links,
+            self.paths.skip_hidden,
             self.video_engine.engine_type,
             self.video_engine.interval,
+            self.video_engine.interval_jitter_secs,
+            toml_optional("vram_low_threshold", self.video_engine.vram_low_threshold),
+            toml_optional(
+                "vram_recovery_threshold",
+                self.video_engine.vram_recovery_threshold
+            ),
+            self.video_engine.per_monitor,
+            self.video_engine.watch,
+            toml_inline(&self.video_engine.tags),
+            self.video_engine.stop_on_exit,
+            self.video_engine.min_switch_interval_ms,
+            self.video_engine.max_video_seconds,
+            toml_optional(
+                "low_vram_fallback_image",
+                toml_string(&self.video_engine.low_vram_fallback_image)
+            ),
+            self.video_engine.mpvpaper_options,
+            self.video_engine.hwdec,
+            self.video_engine.mpvpaper_output,
             self.image_engine.engine_type,
             self.image_engine.interval,
+            self.image_engine.interval_jitter_secs,
             self.image_engine.transition,
             self.image_engine.transition_duration,
+            toml_inline(&self.image_engine.transition_pool),
+            self.image_engine.transition_fps,
+            self.image_engine.transition_step,
+            self.image_engine.fill_mode,
+            self.image_engine.ready_timeout_ms,
+            self.image_engine.per_monitor,
+            self.image_engine.watch,
+            toml_inline(&self.image_engine.tags),
+            self.image_engine.stop_on_exit,
+            self.image_engine.min_switch_interval_ms,
             self.weight.base,
             self.weight.select_penalty,
+            self.weight.initial_spread,
             self.weight.perturbation_ratio,
             self.weight.normalization_threshold,
             self.weight.normalization_target,
             self.weight.shuffle_period,
             self.weight.shuffle_intensity,
+            self.weight.selection_tolerance,
+            self.weight.selection_strategy.as_str(),
+            self.weight.min_cooldown_secs,
+            self.weight.session_no_repeat,
+            toml_inline(&self.weight.skip_reward_tiers),
+            self.weight.feedback_delta,
+            self.weight.decay_per_hour,
+            self.weight.prefer_new_files,
+            toml_optional("weight_floor", self.weight.weight_floor),
+            toml_optional("weight_ceiling", self.weight.weight_ceiling),
             self.vram.enabled,
             self.vram.threshold_percent,
             self.vram.recovery_percent,
             self.vram.check_interval,
+            self.vram.size_penalty_factor,
+            self.vram.size_penalty_threshold,
+            self.schedule.day_start,
+            self.schedule.night_start,
+            toml_optional("video_day_dir", toml_string(&self.schedule.video_day_dir)),
+            toml_optional(
+                "video_night_dir",
+                toml_string(&self.schedule.video_night_dir)
+            ),
+            toml_optional("image_day_dir", toml_string(&self.schedule.image_day_dir)),
+            toml_optional(
+                "image_night_dir",
+                toml_string(&self.schedule.image_night_dir)
+            ),
+            self.image_optimization.enabled,
+            self.image_optimization.cache_dir,
+            self.image_optimization.min_free_mb,
+            self.extension_engine
+                .iter()
+                .map(|(ext, engine)| format!(
+                    "{} = {}",
+                    toml_key(ext),
+                    toml::Value::String(engine.clone())
+                ))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            toml_optional(
+                "on_switch_command",
+                toml_string(&self.hooks.on_switch_command)
+            ),
+            self.palette.enabled,
+            self.palette.colors,
+            self.palette.output,
+            toml_optional(
+                "on_extract_command",
+                toml_string(&self.palette.on_extract_command)
+            ),
         )
     }
 
+    /// 展开路径中的 `~` 与环境变量
+    ///
+    /// - 仅开头的 `~` / `~/` 会被替换为用户主目录，其余位置的 `~` 保持原样
+    /// - `$VAR` 与 `${VAR}` 可出现在任意位置，未定义的变量展开为空字符串并打印警告
     pub fn expand_path(path: &str) -> PathBuf {
-        if path.starts_with("~/") {
+        let expanded = Self::expand_env_vars(path);
+
+        if expanded == "~" {
+            dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
+        } else if let Some(rest) = expanded.strip_prefix("~/") {
             dirs::home_dir()
                 .unwrap_or_else(|| PathBuf::from("/"))
-                .join(&path[2..])
+                .join(rest)
         } else {
-            PathBuf::from(path)
+            PathBuf::from(expanded)
+        }
+    }
+
+    /// 展开字符串中的 `$VAR` 和 `${VAR}`
+    fn expand_env_vars(input: &str) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(pos) = rest.find('$') {
+            output.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+
+            let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+                match braced.find('}') {
+                    Some(end) => (&braced[..end], end + 2),
+                    None => ("", 0),
+                }
+            } else {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            };
+
+            if name.is_empty() {
+                // 不是合法的变量引用，保留原样
+                output.push('$');
+                rest = after;
+                continue;
+            }
+
+            match std::env::var(name) {
+                Ok(value) => output.push_str(&value),
+                Err(_) => log::warn!("环境变量 {} 未定义，已展开为空字符串", name),
+            }
+            rest = &after[consumed..];
         }
+
+        output.push_str(rest);
+        output
     }
 
     /// 根据模式获取缓存文件路径
@@ -390,14 +1340,85 @@ check_interval = {}
         }
     }
 
-    /// 根据模式获取壁纸目录路径
+    /// 根据模式获取当前生效的壁纸目录路径
+    ///
+    /// 配置了 [schedule] 时按本地时间选择白天 / 夜晚目录
     pub fn wallpaper_dir(&self, mode: WallpaperMode) -> PathBuf {
+        let now = chrono::Local::now();
+        let minute_of_day = now.hour() * 60 + now.minute();
+        match self.schedule.dir_at(mode, minute_of_day) {
+            Some(dir) => Self::expand_path(dir),
+            None => self.base_wallpaper_dir(mode),
+        }
+    }
+
+    /// 根据模式获取 [paths] 中配置的壁纸目录路径（不考虑时间段）
+    pub fn base_wallpaper_dir(&self, mode: WallpaperMode) -> PathBuf {
         match mode {
             WallpaperMode::Video => Self::expand_path(&self.paths.video_dir),
             WallpaperMode::Image => Self::expand_path(&self.paths.image_dir),
         }
     }
 
+    /// 获取指定模式可能使用的所有壁纸目录（基础目录与时间段目录）
+    pub fn all_wallpaper_dirs(&self, mode: WallpaperMode) -> Vec<PathBuf> {
+        let schedule = &self.schedule;
+        let scheduled = match mode {
+            WallpaperMode::Video => [&schedule.video_day_dir, &schedule.video_night_dir],
+            WallpaperMode::Image => [&schedule.image_day_dir, &schedule.image_night_dir],
+        };
+        std::iter::once(self.base_wallpaper_dir(mode))
+            .chain(
+                scheduled
+                    .into_iter()
+                    .flatten()
+                    .map(|d| Self::expand_path(d)),
+            )
+            .collect()
+    }
+
+    /// 获取生效的显存阈值（降级阈值, 恢复阈值）
+    ///
+    /// 动态壁纸引擎中的配置优先于 [vram] 中的配置
+    pub fn vram_thresholds(&self) -> (f32, f32) {
+        (
+            self.video_engine
+                .vram_low_threshold
+                .unwrap_or(self.vram.threshold_percent),
+            self.video_engine
+                .vram_recovery_threshold
+                .unwrap_or(self.vram.recovery_percent),
+        )
+    }
+
+    /// 获取编译后的排除规则，无效的模式会被忽略并打印警告
+    pub fn exclude_patterns(&self) -> Vec<glob::Pattern> {
+        self.paths
+            .exclude
+            .iter()
+            .filter_map(|p| match glob::Pattern::new(p) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    log::warn!("无效的排除规则 \"{}\": {}", p, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// 根据文件所在目录推断壁纸模式，不在任何壁纸目录下时返回 None
+    pub fn mode_for_path(&self, path: &Path) -> Option<WallpaperMode> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        [WallpaperMode::Video, WallpaperMode::Image]
+            .into_iter()
+            .find(|&mode| {
+                self.all_wallpaper_dirs(mode).into_iter().any(|dir| {
+                    let dir = fs::canonicalize(&dir).unwrap_or(dir);
+                    path.starts_with(dir)
+                })
+            })
+    }
+
     /// 根据模式获取引擎类型
     pub fn engine_type(&self, mode: WallpaperMode) -> &str {
         match mode {
@@ -406,6 +1427,99 @@ check_interval = {}
         }
     }
 
+    /// 覆盖指定模式的引擎类型（仅本次运行，不写入配置文件）
+    pub fn override_engine(&mut self, mode: WallpaperMode, engine_type: &str) {
+        match mode {
+            WallpaperMode::Video => self.video_engine.engine_type = engine_type.to_string(),
+            WallpaperMode::Image => self.image_engine.engine_type = engine_type.to_string(),
+        }
+    }
+
+    /// 按扩展名指定的引擎（扩展名小写，去掉开头的点）
+    pub fn extension_engines(&self) -> Vec<(String, &str)> {
+        self.extension_engine
+            .iter()
+            .map(|(ext, engine)| {
+                (
+                    ext.trim().trim_start_matches('.').to_lowercase(),
+                    engine.trim(),
+                )
+            })
+            .collect()
+    }
+
+    /// 根据模式获取是否为每个显示器分别设置壁纸
+    pub fn per_monitor(&self, mode: WallpaperMode) -> bool {
+        match mode {
+            WallpaperMode::Video => self.video_engine.per_monitor,
+            WallpaperMode::Image => self.image_engine.per_monitor,
+        }
+    }
+
+    /// 获取展开后的显存不足备用图片路径
+    pub fn low_vram_fallback_image(&self) -> Option<PathBuf> {
+        self.video_engine
+            .low_vram_fallback_image
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+            .map(Self::expand_path)
+    }
+
+    /// 根据模式获取是否监听壁纸目录
+    pub fn watch(&self, mode: WallpaperMode) -> bool {
+        match mode {
+            WallpaperMode::Video => self.video_engine.watch,
+            WallpaperMode::Image => self.image_engine.watch,
+        }
+    }
+
+    /// 根据模式获取最长视频时长（秒），静态壁纸模式不限制
+    pub fn max_video_seconds(&self, mode: WallpaperMode) -> u64 {
+        match mode {
+            WallpaperMode::Video => self.video_engine.max_video_seconds,
+            WallpaperMode::Image => 0,
+        }
+    }
+
+    /// 从动态壁纸切换到静态壁纸时等待新壁纸显示的最长时间
+    pub fn ready_timeout(&self) -> Duration {
+        Duration::from_millis(self.image_engine.ready_timeout_ms)
+    }
+
+    /// 根据模式获取守护进程退出时是否停止引擎
+    pub fn stop_on_exit(&self, mode: WallpaperMode) -> bool {
+        match mode {
+            WallpaperMode::Video => self.video_engine.stop_on_exit,
+            WallpaperMode::Image => self.image_engine.stop_on_exit,
+        }
+    }
+
+    /// 根据模式获取手动切换的最短间隔
+    pub fn min_switch_interval(&self, mode: WallpaperMode) -> Duration {
+        Duration::from_millis(match mode {
+            WallpaperMode::Video => self.video_engine.min_switch_interval_ms,
+            WallpaperMode::Image => self.image_engine.min_switch_interval_ms,
+        })
+    }
+
+    /// 获取当前生效的标签过滤（小写）
+    ///
+    /// 通过 `lianwall tag` 设置的运行时标签优先于配置文件中的 tags
+    pub fn active_tags(&self, mode: WallpaperMode) -> Vec<String> {
+        match Self::load_active_tags(mode) {
+            Some(tags) => normalize_tags(&tags),
+            None => self.configured_tags(mode),
+        }
+    }
+
+    /// 获取配置文件中的标签过滤（小写），不读取运行时标签
+    pub fn configured_tags(&self, mode: WallpaperMode) -> Vec<String> {
+        normalize_tags(match mode {
+            WallpaperMode::Video => &self.video_engine.tags,
+            WallpaperMode::Image => &self.image_engine.tags,
+        })
+    }
+
     /// 根据模式获取切换间隔
     pub fn interval(&self, mode: WallpaperMode) -> u64 {
         match mode {
@@ -414,6 +1528,14 @@ check_interval = {}
         }
     }
 
+    /// 根据模式获取切换间隔的随机抖动范围
+    pub fn interval_jitter(&self, mode: WallpaperMode) -> u64 {
+        match mode {
+            WallpaperMode::Video => self.video_engine.interval_jitter_secs,
+            WallpaperMode::Image => self.image_engine.interval_jitter_secs,
+        }
+    }
+
     /// 获取展开后的视频目录路径（兼容旧代码）
     pub fn video_path(&self) -> PathBuf {
         Self::expand_path(&self.paths.video_dir)
@@ -456,4 +1578,385 @@ check_interval = {}
             WallpaperMode::Video
         }
     }
+
+    /// 获取暂停状态文件路径
+    pub fn pause_state_path() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.cache"))
+            .join("lianwall/paused")
+    }
+
+    /// 设置守护进程暂停状态
+    pub fn save_paused(paused: bool) {
+        let path = Self::pause_state_path();
+        if paused {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).ok();
+            }
+            fs::write(&path, "").ok();
+        } else {
+            fs::remove_file(&path).ok();
+        }
+    }
+
+    /// 读取守护进程是否处于暂停状态
+    pub fn is_paused() -> bool {
+        Self::pause_state_path().exists()
+    }
+
+    /// 获取固定壁纸状态文件路径
+    pub fn pin_state_path() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.cache"))
+            .join("lianwall/pinned")
+    }
+
+    /// 保存固定的壁纸，传入 None 表示取消固定
+    pub fn save_pinned(path: Option<&Path>) {
+        let state_path = Self::pin_state_path();
+        match path {
+            Some(path) => {
+                if let Some(parent) = state_path.parent() {
+                    fs::create_dir_all(parent).ok();
+                }
+                fs::write(&state_path, path.to_string_lossy().as_bytes()).ok();
+            }
+            None => {
+                fs::remove_file(&state_path).ok();
+            }
+        }
+    }
+
+    /// 读取当前固定的壁纸
+    pub fn load_pinned() -> Option<PathBuf> {
+        let content = fs::read_to_string(Self::pin_state_path()).ok()?;
+        let path = content.trim();
+        if path.is_empty() {
+            None
+        } else {
+            Some(PathBuf::from(path))
+        }
+    }
+
+    /// 获取运行时标签状态文件路径（每种模式一个）
+    pub fn active_tags_state_path(mode: WallpaperMode) -> PathBuf {
+        let name = match mode {
+            WallpaperMode::Video => "lianwall/active_tags_video",
+            WallpaperMode::Image => "lianwall/active_tags_image",
+        };
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.cache"))
+            .join(name)
+    }
+
+    /// 保存运行时标签（每行一个），传入 None 表示恢复使用配置文件中的 tags
+    pub fn save_active_tags(mode: WallpaperMode, tags: Option<&[String]>) {
+        let state_path = Self::active_tags_state_path(mode);
+        match tags {
+            Some(tags) => {
+                if let Some(parent) = state_path.parent() {
+                    fs::create_dir_all(parent).ok();
+                }
+                fs::write(&state_path, tags.join("\n")).ok();
+            }
+            None => {
+                fs::remove_file(&state_path).ok();
+            }
+        }
+    }
+
+    /// 读取运行时标签，未设置时返回 None（空列表表示不限制）
+    pub fn load_active_tags(mode: WallpaperMode) -> Option<Vec<String>> {
+        let content = fs::read_to_string(Self::active_tags_state_path(mode)).ok()?;
+        Some(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+
+    /// 获取当前壁纸状态文件路径
+    pub fn current_wallpaper_state_path() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.cache"))
+            .join("lianwall/current_wallpaper")
+    }
+
+    /// 保存当前显示的壁纸（多显示器时每行一个），同时记录切换时间
+    pub fn save_current_wallpapers<P: AsRef<Path>>(paths: &[P]) {
+        let state_path = Self::current_wallpaper_state_path();
+        if let Some(parent) = state_path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        let content: Vec<String> = paths
+            .iter()
+            .map(|p| p.as_ref().to_string_lossy().into_owned())
+            .collect();
+        fs::write(&state_path, content.join("\n")).ok();
+        Self::save_last_switch();
+    }
+
+    /// 获取上次切换时间状态文件路径
+    pub fn last_switch_state_path() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.cache"))
+            .join("lianwall/last_switch")
+    }
+
+    /// 记录切换时间（Unix 毫秒）
+    pub fn save_last_switch() {
+        let path = Self::last_switch_state_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        fs::write(&path, unix_millis().to_string()).ok();
+    }
+
+    /// 检查距上次切换是否已超过 min_switch_interval_ms，超过时立即记录本次切换时间
+    ///
+    /// 在切换开始前调用：引擎启动需要时间，等切换完成再记录的话，
+    /// 快捷键连按时第二次调用会在第一次完成前通过检查。间隔不足时返回还需等待的时间
+    pub fn claim_switch(&self, mode: WallpaperMode) -> Result<(), Duration> {
+        let last = fs::read_to_string(Self::last_switch_state_path())
+            .ok()
+            .and_then(|s| s.trim().parse().ok());
+        let now = unix_millis();
+        if let Some(wait) = switch_wait(last, now, self.min_switch_interval(mode)) {
+            return Err(wait);
+        }
+        Self::save_last_switch();
+        Ok(())
+    }
+
+    /// 读取当前显示的壁纸
+    pub fn load_current_wallpapers() -> Vec<PathBuf> {
+        fs::read_to_string(Self::current_wallpaper_state_path())
+            .map(|content| {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// 标签统一转为小写并去掉空标签
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    tags.iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// 格式化可选配置项，未设置时输出为注释
+fn toml_optional<T: fmt::Display>(key: &str, value: Option<T>) -> String {
+    match value {
+        Some(v) => format!("{} = {}", key, v),
+        None => format!("# {} = ", key),
+    }
+}
+
+/// 格式化 TOML 键，包含裸键不允许的字符（如 "."）时加引号
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
+    }
+}
+
+/// 将可选字符串格式化为 TOML 字符串字面量
+fn toml_string(value: &Option<String>) -> Option<toml::Value> {
+    value.clone().map(toml::Value::String)
+}
+
+/// 将列表格式化为 TOML 行内数组
+fn toml_inline<T: Serialize>(items: &[T]) -> String {
+    toml::Value::try_from(items)
+        .map(|v| v.to_string())
+        .unwrap_or_else(|_| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_roundtrip() {
+        let config = Config::default();
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.weight.base, config.weight.base);
+        assert_eq!(parsed.paths.video_dir, config.paths.video_dir);
+
+        let mut config = Config::default();
+        config.paths.exclude = vec!["*.tmp".to_string(), "**/private/*".to_string()];
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.paths.exclude, config.paths.exclude);
+
+        let mut config = Config::default();
+        config.video_engine.vram_low_threshold = Some(15.0);
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(
+            parsed.vram_thresholds(),
+            (15.0, config.vram.recovery_percent)
+        );
+
+        let mut config = Config::default();
+        config.image_engine.transition_pool = vec!["wipe".to_string(), "grow".to_string()];
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(
+            parsed.image_engine.transition_pool,
+            config.image_engine.transition_pool
+        );
+
+        let mut config = Config::default();
+        config.weight.skip_reward_tiers = vec![(0, 1.0), (3, 2.5)];
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(
+            parsed.weight.skip_reward_tiers,
+            config.weight.skip_reward_tiers
+        );
+
+        let mut config = Config::default();
+        config
+            .extension_engine
+            .insert("apng".to_string(), "mpvpaper".to_string());
+        config
+            .extension_engine
+            .insert(".webp".to_string(), "mpvpaper".to_string());
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.extension_engine, config.extension_engine);
+    }
+
+    #[test]
+    fn test_schedule_day_night_dirs() {
+        let mut config = Config::default();
+        config.schedule.video_day_dir = Some("~/Videos/day".to_string());
+        config.schedule.video_night_dir = Some("~/Videos/night".to_string());
+        let parsed = Config::parse(&config.to_toml_with_comments()).unwrap();
+        let schedule = &parsed.schedule;
+
+        assert_eq!(
+            schedule.dir_at(WallpaperMode::Video, 5 * 60 + 59),
+            Some("~/Videos/night")
+        );
+        assert_eq!(
+            schedule.dir_at(WallpaperMode::Video, 6 * 60),
+            Some("~/Videos/day")
+        );
+        assert_eq!(
+            schedule.dir_at(WallpaperMode::Video, 18 * 60),
+            Some("~/Videos/night")
+        );
+        assert_eq!(schedule.dir_at(WallpaperMode::Image, 12 * 60), None);
+
+        // 白天跨越午夜
+        let schedule = ScheduleConfig {
+            day_start: "22:00".to_string(),
+            night_start: "04:30".to_string(),
+            ..ScheduleConfig::default()
+        };
+        assert!(schedule.is_day(23 * 60));
+        assert!(schedule.is_day(60));
+        assert!(!schedule.is_day(12 * 60));
+    }
+
+    #[test]
+    fn test_parse_malformed_reports_field() {
+        let content = Config::default()
+            .to_toml_with_comments()
+            .replace("interval = 600", "interval = \"ten minutes\"");
+        let err = Config::parse(&content).unwrap_err();
+        let msg = ConfigError::Parse(PathBuf::from("config.toml"), err).to_string();
+        assert!(msg.contains("interval"), "{}", msg);
+        assert!(msg.contains("line"), "{}", msg);
+    }
+
+    #[test]
+    fn test_load_from_reports_unwritable_default_config() {
+        let dir = tempfile::tempdir().unwrap();
+        // 父目录是普通文件，无法创建配置目录
+        let blocker = dir.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let path = blocker.join("lianwall/config.toml");
+
+        let err = Config::load_from(&path).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::Io(ref p, _) if *p == path),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_wallpaper_mode_from_str() {
+        assert_eq!("video".parse(), Ok(WallpaperMode::Video));
+        assert_eq!("Picture".parse(), Ok(WallpaperMode::Image));
+        assert_eq!(" static ".parse(), Ok(WallpaperMode::Image));
+        assert!("viideo".parse::<WallpaperMode>().is_err());
+        assert!("".parse::<WallpaperMode>().is_err());
+    }
+
+    #[test]
+    fn test_switch_wait() {
+        let min = Duration::from_millis(500);
+        assert_eq!(switch_wait(None, 10_000, min), None);
+        assert_eq!(
+            switch_wait(Some(9_800), 10_000, min),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(switch_wait(Some(9_500), 10_000, min), None);
+        assert_eq!(switch_wait(Some(12_000), 10_000, min), Some(min));
+        assert_eq!(switch_wait(Some(9_999), 10_000, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_expand_path_tilde() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(Config::expand_path("~/Videos"), home.join("Videos"));
+        assert_eq!(
+            Config::expand_path("/data/~backup"),
+            PathBuf::from("/data/~backup")
+        );
+    }
+
+    #[test]
+    fn test_expand_path_env_vars() {
+        let home = std::env::var("HOME").unwrap();
+        assert_eq!(
+            Config::expand_path("$HOME/x"),
+            PathBuf::from(format!("{}/x", home))
+        );
+
+        // 不修改环境变量（其他测试会并发读取），按当前环境计算期望值
+        let xdg = std::env::var("XDG_CONFIG_HOME").unwrap_or_default();
+        assert_eq!(
+            Config::expand_path("${XDG_CONFIG_HOME}/lianwall"),
+            PathBuf::from(format!("{}/lianwall", xdg))
+        );
+
+        assert_eq!(
+            Config::expand_path("$LIANWALL_SURELY_UNDEFINED/a"),
+            PathBuf::from("/a")
+        );
+    }
+
+    #[test]
+    fn test_expand_path_plain() {
+        assert_eq!(
+            Config::expand_path("/usr/share/backgrounds"),
+            PathBuf::from("/usr/share/backgrounds")
+        );
+        assert_eq!(Config::expand_path("price$"), PathBuf::from("price$"));
+    }
 }
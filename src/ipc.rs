@@ -0,0 +1,225 @@
+/// 守护进程控制接口
+///
+/// 守护进程在 Unix socket 上监听单行文本命令（next、pause、resume、status、set <path>），
+/// 每个连接处理一条命令并返回一行 JSON 回复；CLI 检测到守护进程在运行时通过它转发命令，
+/// 避免每次按快捷键都重新扫描目录和读取缓存
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use std::time::Duration;
+
+/// 等待守护进程处理命令的最长时间（切换壁纸可能需要启动引擎）
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 等待客户端发送命令或读取回复的最长时间，避免不发送数据的连接阻塞监听线程
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 控制命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    /// 切换到下一张壁纸
+    Next,
+    /// 暂停自动轮换
+    Pause,
+    /// 恢复自动轮换
+    Resume,
+    /// 获取状态和壁纸列表
+    Status,
+    /// 显示指定壁纸
+    Set(PathBuf),
+}
+
+impl Request {
+    /// 解析一行命令
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        match (command, arg.is_empty()) {
+            ("next", true) => Ok(Request::Next),
+            ("pause", true) => Ok(Request::Pause),
+            ("resume", true) => Ok(Request::Resume),
+            ("status", true) => Ok(Request::Status),
+            ("set", false) => Ok(Request::Set(PathBuf::from(arg))),
+            _ => Err(format!("未知命令: {}", line)),
+        }
+    }
+
+    /// 格式化为一行命令
+    pub fn to_line(&self) -> String {
+        match self {
+            Request::Next => "next".to_string(),
+            Request::Pause => "pause".to_string(),
+            Request::Resume => "resume".to_string(),
+            Request::Status => "status".to_string(),
+            Request::Set(path) => format!("set {}", path.display()),
+        }
+    }
+}
+
+/// 命令回复
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl Reply {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: message.into(),
+        }
+    }
+
+    pub fn err(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// 获取控制 socket 路径（优先 $XDG_RUNTIME_DIR）
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("lianwall.sock")
+}
+
+/// 守护进程端的控制接口
+///
+/// 监听线程把收到的命令通过 channel 交给守护进程主循环处理
+pub struct IpcServer {
+    path: PathBuf,
+    rx: Receiver<(Request, Sender<Reply>)>,
+}
+
+impl IpcServer {
+    /// 开始监听，已有守护进程在监听或无法创建 socket 时返回 None
+    pub fn start() -> Option<Self> {
+        let path = socket_path();
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                log::warn!("控制接口 {} 已被其他守护进程占用", path.display());
+                return None;
+            }
+            // 上次守护进程异常退出留下的 socket 文件
+            std::fs::remove_file(&path).ok();
+        }
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::warn!("无法创建控制接口 {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        log::info!("控制接口: {}", path.display());
+
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle_connection(stream, &tx);
+            }
+        });
+
+        Some(Self { path, rx })
+    }
+
+    /// 等待命令，超时返回 None（用作守护进程主循环的节拍）
+    pub fn wait(&self, timeout: Duration) -> Option<(Request, Sender<Reply>)> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// 处理一个连接：读取一行命令，交给主循环，写回一行 JSON 回复
+fn handle_connection(stream: UnixStream, tx: &Sender<(Request, Sender<Reply>)>) {
+    if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_err()
+    {
+        return;
+    }
+
+    let mut line = String::new();
+    if BufReader::new(&stream).read_line(&mut line).is_err() {
+        return;
+    }
+
+    let reply = match Request::parse(&line) {
+        Ok(request) => {
+            log::debug!("收到控制命令: {}", request.to_line());
+            let (reply_tx, reply_rx) = channel();
+            if tx.send((request, reply_tx)).is_err() {
+                return;
+            }
+            reply_rx
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| Reply::err("守护进程处理超时"))
+        }
+        Err(e) => Reply::err(e),
+    };
+
+    if let Ok(json) = serde_json::to_string(&reply) {
+        let mut stream = &stream;
+        stream.write_all(json.as_bytes()).ok();
+        stream.write_all(b"\n").ok();
+    }
+}
+
+/// 向运行中的守护进程发送命令
+///
+/// 没有守护进程在监听时返回 None，调用方应回退到直接执行
+pub fn send(request: &Request) -> Option<Reply> {
+    let mut stream = UnixStream::connect(socket_path()).ok()?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT)).ok();
+    stream
+        .write_all(format!("{}\n", request.to_line()).as_bytes())
+        .ok()?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).ok()?;
+    Some(serde_json::from_str(&line).unwrap_or_else(|e| Reply::err(format!("无效的回复: {}", e))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_roundtrip() {
+        for request in [
+            Request::Next,
+            Request::Pause,
+            Request::Resume,
+            Request::Status,
+            Request::Set(PathBuf::from("/tmp/my wallpaper.mp4")),
+        ] {
+            assert_eq!(Request::parse(&request.to_line()), Ok(request));
+        }
+        assert!(Request::parse("set").is_err());
+        assert!(Request::parse("next now").is_err());
+        assert!(Request::parse("reboot").is_err());
+    }
+
+    #[test]
+    fn test_silent_client_does_not_block_listener() {
+        let (server, _client) = UnixStream::pair().unwrap();
+        let (tx, rx) = channel();
+
+        let start = std::time::Instant::now();
+        handle_connection(server, &tx);
+        assert!(start.elapsed() < REPLY_TIMEOUT);
+        assert!(rx.try_recv().is_err());
+    }
+}
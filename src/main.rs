@@ -1,29 +1,51 @@
 mod algorithm;
 mod command;
 mod config;
+mod ipc;
 mod manager;
+mod monitor;
+mod optimize;
+mod palette;
 mod paperengine;
+mod systemd;
 mod vram;
+mod watcher;
 
+use log::{debug, error, info, warn};
+use rand::Rng;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use command::{Cli, Commands};
-use config::{Config, WallpaperMode};
-use manager::WallManager;
+use command::{Cli, Commands, TagAction};
+use config::{Config, VramConfig, WallpaperMode, normalize_tags};
+use ipc::{IpcServer, Reply, Request};
+use manager::{ListFormat, SortKey, WallManager, same_content};
+use paperengine::create_engine;
 use vram::{get_vram_info, is_vram_low, is_vram_recovered};
+use watcher::DirWatcher;
 
-fn parse_mode(mode_str: &str) -> WallpaperMode {
-    match mode_str.to_lowercase().as_str() {
-        "picture" | "image" | "static" => WallpaperMode::Image,
-        _ => WallpaperMode::Video,
-    }
-}
+/// 守护进程检查固定的壁纸是否仍在显示的间隔
+const PIN_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 退出码：成功
+const EXIT_OK: i32 = 0;
+/// 退出码：壁纸切换失败
+const EXIT_FAILURE: i32 = 1;
+/// 退出码：壁纸目录不存在
+const EXIT_MISSING_DIR: i32 = 2;
 
 /// 显存监控状态
 struct VramMonitorState {
     /// 是否因显存不足而降级到静态壁纸
     degraded: bool,
+    /// 降级时没有可用的静态壁纸（或显示的是备用图片），动态壁纸已暂停
+    paused: bool,
+    /// 降级时显示的是 low_vram_fallback_image
+    fallback: bool,
     /// 上次检查时间
     last_check: Instant,
 }
@@ -32,6 +54,8 @@ impl Default for VramMonitorState {
     fn default() -> Self {
         Self {
             degraded: false,
+            paused: false,
+            fallback: false,
             last_check: Instant::now(),
         }
     }
@@ -39,34 +63,169 @@ impl Default for VramMonitorState {
 
 fn main() {
     let cli = Cli::parse_args();
-    let config = Config::load();
+    init_logger(cli.verbose, cli.quiet);
+    // 编辑配置文件不需要（也不应该）先加载可能有错误的配置
+    if let Commands::Edit = cli.command {
+        std::process::exit(edit_config(cli.config.as_deref()));
+    }
 
-    match cli.command {
-        Commands::Daemon => {
-            run_daemon(config);
-        }
+    let mut config = Config::load_or_default(cli.config.as_deref());
+
+    if let Some(engine) = &cli.engine {
+        let mode = command_mode(&cli.command);
+        info!("临时使用引擎: {} ({:?} 模式)", engine, mode);
+        config.override_engine(mode, engine);
+    }
+
+    let code = run(cli.command, config);
+    palette::wait();
+    std::process::exit(code);
+}
+
+/// 命令作用的壁纸模式（用于 --engine 覆盖），未指定模式的命令使用当前模式
+fn command_mode(command: &Commands) -> WallpaperMode {
+    let explicit = match command {
+        Commands::Daemon { .. } | Commands::Video => return WallpaperMode::Video,
+        Commands::Picture => return WallpaperMode::Image,
+        Commands::Reset { mode, .. } => Some(*mode),
+        Commands::Status { mode, .. }
+        | Commands::Stats { mode }
+        | Commands::Export { mode, .. }
+        | Commands::Import { mode, .. }
+        | Commands::Verify { mode, .. }
+        | Commands::Dedup { mode, .. } => *mode,
+        _ => None,
+    };
+    explicit.unwrap_or_else(Config::load_current_mode)
+}
+
+/// 创建壁纸管理器并应用运行时标签过滤，失败时打印错误并返回 None
+fn open_manager(config: Config, mode: WallpaperMode) -> Option<WallManager> {
+    WallManager::try_new(config, mode, false)
+        .map(with_active_tags)
+        .map_err(|e| eprintln!("❌ {}", e))
+        .ok()
+}
+
+/// 创建需要切换壁纸的管理器，同时检查引擎程序是否已安装
+fn open_engine_manager(config: Config, mode: WallpaperMode) -> Option<WallManager> {
+    WallManager::try_new(config, mode, false)
+        .and_then(|m| m.ensure_engine_available().map(|_| m))
+        .map(with_active_tags)
+        .map_err(|e| eprintln!("❌ {}", e))
+        .ok()
+}
+
+/// 创建演练模式的壁纸管理器：不检查引擎程序，也不写入缓存和扫描索引
+fn open_dry_run_manager(config: Config, mode: WallpaperMode) -> Option<WallManager> {
+    WallManager::try_new(config, mode, true)
+        .map(with_active_tags)
+        .map_err(|e| eprintln!("❌ {}", e))
+        .ok()
+}
+
+/// 使用 `lianwall tag` 设置的运行时标签（未设置时为配置文件中的 tags）
+fn with_active_tags(mut manager: WallManager) -> WallManager {
+    manager.tag_filter = manager.config.active_tags(manager.mode);
+    manager
+}
 
-        Commands::Next => {
+/// 初始化日志，默认 info 级别，可通过 RUST_LOG 或 -v / -q 调整
+fn init_logger(verbose: u8, quiet: bool) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+
+    if quiet {
+        builder.filter_level(log::LevelFilter::Warn);
+    } else if verbose == 1 {
+        builder.filter_level(log::LevelFilter::Debug);
+    } else if verbose > 1 {
+        builder.filter_level(log::LevelFilter::Trace);
+    }
+
+    builder.format_target(false).init();
+}
+
+/// 执行命令，返回进程退出码
+fn run(command: Commands, config: Config) -> i32 {
+    match command {
+        Commands::Daemon { dry_run, once } => run_daemon(config, dry_run, once),
+
+        Commands::Next {
+            count,
+            dry_run,
+            tag,
+            output,
+        } => {
+            // 守护进程在运行时交给它切换，无需重新扫描目录和读取缓存
+            if count == 1
+                && !dry_run
+                && tag.is_none()
+                && output.is_none()
+                && let Some(code) = forward_to_daemon(&Request::Next)
+            {
+                return code;
+            }
             let current_mode = Config::load_current_mode();
-            let mut manager = WallManager::new(config, current_mode);
+            let opened = if dry_run {
+                open_dry_run_manager(config, current_mode)
+            } else {
+                open_engine_manager(config, current_mode)
+            };
+            let Some(mut manager) = opened else {
+                return EXIT_FAILURE;
+            };
+            if let Some(tag) = tag {
+                manager.tag_filter = vec![tag.trim().to_lowercase()];
+            }
+            if !dry_run && let Err(wait) = manager.config.claim_switch(current_mode) {
+                eprintln!("⏳ {}", switch_too_soon(wait));
+                return EXIT_FAILURE;
+            }
             let mode_desc = match current_mode {
                 WallpaperMode::Video => "动态壁纸",
                 WallpaperMode::Image => "静态壁纸",
             };
-            match manager.next() {
-                Ok(_) => println!("✅ {}切换成功", mode_desc),
-                Err(e) => eprintln!("❌ 切换失败: {}", e),
+
+            let mut code = EXIT_OK;
+            for i in 0..count {
+                if i > 0 {
+                    // 给引擎一点时间完成上一次切换
+                    thread::sleep(Duration::from_millis(500));
+                }
+                let result = match &output {
+                    Some(output) => manager.next_on_output(output),
+                    None => manager.next(),
+                };
+                match result {
+                    Ok(_) if dry_run => {}
+                    Ok(_) => println!("✅ {}切换成功", mode_desc),
+                    Err(e) => {
+                        eprintln!("❌ 切换失败: {}", e);
+                        code = EXIT_FAILURE;
+                    }
+                }
             }
+            code
         }
 
         Commands::Video => {
             let _ = std::process::Command::new("swww").arg("kill").status();
 
-            let mut manager = WallManager::new(config.clone(), WallpaperMode::Video);
+            let Some(mut manager) = open_engine_manager(config.clone(), WallpaperMode::Video)
+            else {
+                return EXIT_FAILURE;
+            };
             Config::save_current_mode(WallpaperMode::Video);
             match manager.next() {
-                Ok(_) => println!("🎬 切换到动态壁纸模式"),
-                Err(e) => eprintln!("❌ 切换失败: {}", e),
+                Ok(_) => {
+                    println!("🎬 切换到动态壁纸模式");
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("❌ 切换失败: {}", e);
+                    EXIT_FAILURE
+                }
             }
         }
 
@@ -84,35 +243,117 @@ fn main() {
             // }
 
             // 备选逻辑：先启动 swww 并设置壁纸（在 mpvpaper 下面准备好）
-            let mut manager = WallManager::new(config.clone(), WallpaperMode::Image);
+            let Some(mut manager) = open_engine_manager(config.clone(), WallpaperMode::Image)
+            else {
+                return EXIT_FAILURE;
+            };
             Config::save_current_mode(WallpaperMode::Image);
             match manager.next() {
                 Ok(_) => {
-                    // 等待 swww 完全渲染完成
-                    thread::sleep(Duration::from_millis(1000));
-                    // swww 准备好后再杀 mpvpaper，实现平滑切换
+                    // 确认 swww 已显示新壁纸后再杀 mpvpaper，实现平滑切换
+                    if !manager.wait_until_shown(config.ready_timeout()) {
+                        debug!("未能确认新壁纸已显示，直接停止 mpvpaper");
+                    }
                     let _ = std::process::Command::new("pkill").arg("mpvpaper").status();
                     println!("🖼️ 切换到静态壁纸模式");
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("❌ 切换失败: {}", e);
+                    EXIT_FAILURE
                 }
-                Err(e) => eprintln!("❌ 切换失败: {}", e),
             }
         }
 
-        Commands::Reset { mode } => {
-            let mode = parse_mode(&mode);
-            let mut manager = WallManager::new(config, mode);
-            manager.reset();
-            println!("✅ 热重载完成");
+        Commands::Reset { mode, hard } => {
+            let Some(mut manager) = open_manager(config, mode) else {
+                return EXIT_FAILURE;
+            };
+            if !hard {
+                manager.reset();
+                println!("✅ 热重载完成");
+                return EXIT_OK;
+            }
+            match manager.hard_reset() {
+                Ok(discarded) => {
+                    println!(
+                        "✅ 已丢弃 {} 条权重记录，{} 个壁纸恢复初始权重",
+                        discarded,
+                        manager.wallpapers.len()
+                    );
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("❌ 无法删除缓存文件: {}", e);
+                    EXIT_FAILURE
+                }
+            }
         }
 
-        Commands::Status { mode } => {
-            let mode = match mode {
-                Some(m) => parse_mode(&m),
-                None => Config::load_current_mode(),
+        Commands::Status {
+            mode,
+            json,
+            format,
+            tag,
+            sort,
+        } => {
+            if mode.is_none()
+                && !json
+                && format == ListFormat::Table
+                && tag.is_none()
+                && sort == SortKey::Value
+                && let Some(code) = forward_to_daemon(&Request::Status)
+            {
+                return code;
+            }
+            let mode = mode.unwrap_or_else(Config::load_current_mode);
+            let dir_exists = config.wallpaper_dir(mode).is_dir();
+            let Some(mut manager) = open_manager(config, mode) else {
+                return EXIT_FAILURE;
             };
-            let manager = WallManager::new(config, mode);
-            println!("{}", manager.status());
-            println!("{}", manager.list_wallpapers());
+            manager.tag_filter = tag.map(|t| t.trim().to_lowercase()).into_iter().collect();
+            if json {
+                match manager.status_json() {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        return EXIT_FAILURE;
+                    }
+                }
+            } else {
+                let list = match manager.list_wallpapers(format, sort) {
+                    Ok(list) => list,
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        return EXIT_FAILURE;
+                    }
+                };
+                if format == ListFormat::Table {
+                    println!("{}", manager.status());
+                    println!("{}", list);
+                } else {
+                    print!("{}", list);
+                }
+            }
+            if dir_exists {
+                EXIT_OK
+            } else {
+                EXIT_MISSING_DIR
+            }
+        }
+
+        Commands::Stats { mode } => {
+            let mode = mode.unwrap_or_else(Config::load_current_mode);
+            let dir_exists = config.wallpaper_dir(mode).is_dir();
+            let Some(manager) = open_manager(config, mode) else {
+                return EXIT_FAILURE;
+            };
+            print!("{}", manager.play_stats());
+            if dir_exists {
+                EXIT_OK
+            } else {
+                EXIT_MISSING_DIR
+            }
         }
 
         Commands::Kill => {
@@ -125,64 +366,782 @@ fn main() {
                 .stderr(std::process::Stdio::null())
                 .status();
 
+            // 停止 swaybg / wpaperd（如果使用）
+            for engine in ["swaybg", "wpaperd"] {
+                let _ = std::process::Command::new("pkill")
+                    .args(["-x", engine])
+                    .status();
+            }
+
             println!("✅ 已停止所有壁纸引擎");
 
             // 杀掉所有 lianwall 进程（包括 daemon 和自己）
             let _ = std::process::Command::new("killall")
                 .arg("lianwall")
                 .status();
+            EXIT_OK
+        }
+
+        Commands::Like { path } => adjust_weight(config, &path, true),
+
+        Commands::Dislike { path } => adjust_weight(config, &path, false),
+
+        Commands::Pin { path } => {
+            if let Some(p) = &path {
+                let path = Config::expand_path(p);
+                let path = std::fs::canonicalize(&path).unwrap_or(path);
+                if let Some(reply) = ipc::send(&Request::Set(path.clone())) {
+                    if !reply.ok {
+                        eprintln!("❌ 固定失败: {}", reply.message);
+                        return EXIT_FAILURE;
+                    }
+                    Config::save_pinned(Some(&path));
+                    println!("📌 已固定: {}", path.display());
+                    return EXIT_OK;
+                }
+            }
+            let current_mode = Config::load_current_mode();
+            let Some(mut manager) = open_manager(config, current_mode) else {
+                return EXIT_FAILURE;
+            };
+
+            let result = match path {
+                Some(p) => {
+                    let path = Config::expand_path(&p);
+                    if manager
+                        .config
+                        .mode_for_path(&path)
+                        .is_some_and(|m| m != current_mode)
+                    {
+                        Err("该壁纸不属于当前模式，请先切换模式".to_string())
+                    } else if let Err(wait) = manager.config.claim_switch(current_mode) {
+                        Err(switch_too_soon(wait))
+                    } else {
+                        manager.show(&path).map(|_| path)
+                    }
+                }
+                None => manager
+                    .current()
+                    .map(|p| p.to_path_buf())
+                    .ok_or_else(|| "当前没有正在显示的壁纸".to_string()),
+            };
+
+            match result {
+                Ok(path) => {
+                    let path = std::fs::canonicalize(&path).unwrap_or(path);
+                    Config::save_pinned(Some(&path));
+                    println!("📌 已固定: {}", path.display());
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("❌ 固定失败: {}", e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+
+        Commands::Unpin => {
+            Config::save_pinned(None);
+            println!("✅ 已取消固定，恢复自动轮换");
+            EXIT_OK
+        }
+
+        Commands::Tag { action } => edit_active_tags(&config, action),
+
+        Commands::Current => {
+            let paths = Config::load_current_wallpapers();
+            if paths.is_empty() {
+                return EXIT_FAILURE;
+            }
+            for path in paths {
+                println!("{}", path.display());
+            }
+            EXIT_OK
+        }
+
+        Commands::ShowConfig => show_config(&config),
+
+        Commands::Edit => edit_config(config.source_path.as_deref()),
+
+        Commands::Export { mode, out } => {
+            let mode = mode.unwrap_or_else(Config::load_current_mode);
+            let Some(manager) = open_manager(config, mode) else {
+                return EXIT_FAILURE;
+            };
+            let out = Config::expand_path(&out);
+            match manager.export_cache(&out) {
+                Ok(count) => {
+                    println!("✅ 已导出 {} 条权重记录到 {}", count, out.display());
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("❌ 导出失败: {}", e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+
+        Commands::Import { mode, file } => {
+            let mode = mode.unwrap_or_else(Config::load_current_mode);
+            let Some(mut manager) = open_manager(config, mode) else {
+                return EXIT_FAILURE;
+            };
+            match manager.import_cache(&Config::expand_path(&file)) {
+                Ok((merged, ignored)) => {
+                    println!(
+                        "✅ 已合并 {} 条权重记录，忽略 {} 条（不在当前壁纸库中）",
+                        merged, ignored
+                    );
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("❌ 导入失败: {}", e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+
+        Commands::Verify { mode, fix } => {
+            let mode = mode.unwrap_or_else(Config::load_current_mode);
+            match manager::verify_cache(&config, mode, fix) {
+                Ok(report) => print_verify_report(&report),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+
+        Commands::Dedup {
+            mode,
+            remove,
+            delete_files,
+            clear,
+        } => {
+            let mode = mode.unwrap_or_else(Config::load_current_mode);
+            let Some(mut manager) = open_manager(config, mode) else {
+                return EXIT_FAILURE;
+            };
+            if clear {
+                return match manager.clear_duplicates() {
+                    Ok(cleared) => {
+                        println!("✅ 已清除 {} 个重复标记", cleared);
+                        EXIT_OK
+                    }
+                    Err(e) => {
+                        eprintln!("❌ 无法写入缓存文件: {}", e);
+                        EXIT_FAILURE
+                    }
+                };
+            }
+            dedup_wallpapers(&mut manager, remove, delete_files)
+        }
+
+        Commands::Completions { shell } => {
+            Cli::print_completions(shell);
+            EXIT_OK
+        }
+
+        Commands::Pause => {
+            if let Some(code) = forward_to_daemon(&Request::Pause) {
+                return code;
+            }
+            Config::save_paused(true);
+            println!("⏸️ 已暂停自动轮换");
+            EXIT_OK
+        }
+
+        Commands::Resume => {
+            if let Some(code) = forward_to_daemon(&Request::Resume) {
+                return code;
+            }
+            Config::save_paused(false);
+            println!("▶️ 已恢复自动轮换");
+            EXIT_OK
+        }
+    }
+}
+
+/// 把命令转发给运行中的守护进程并输出回复，没有守护进程时返回 None
+fn forward_to_daemon(request: &Request) -> Option<i32> {
+    let reply = ipc::send(request)?;
+    if reply.ok {
+        println!("{}", reply.message);
+        Some(EXIT_OK)
+    } else {
+        eprintln!("❌ {}", reply.message);
+        Some(EXIT_FAILURE)
+    }
+}
+
+/// 修改运行时标签并输出修改后的标签过滤
+fn edit_active_tags(config: &Config, action: TagAction) -> i32 {
+    let resolve = |mode: Option<WallpaperMode>| mode.unwrap_or_else(Config::load_current_mode);
+
+    let mode = match action {
+        TagAction::Add { tags, mode } => {
+            let mode = resolve(mode);
+            let mut active = config.active_tags(mode);
+            for tag in normalize_tags(&tags) {
+                if !active.contains(&tag) {
+                    active.push(tag);
+                }
+            }
+            Config::save_active_tags(mode, Some(&active));
+            mode
+        }
+        TagAction::Remove { tags, mode } => {
+            let mode = resolve(mode);
+            let removed = normalize_tags(&tags);
+            let mut active = config.active_tags(mode);
+            active.retain(|t| !removed.contains(t));
+            Config::save_active_tags(mode, Some(&active));
+            mode
+        }
+        TagAction::Clear { mode } => {
+            let mode = resolve(mode);
+            Config::save_active_tags(mode, None);
+            mode
+        }
+    };
+
+    let active = config.active_tags(mode);
+    if active.is_empty() {
+        println!("🏷️ 标签过滤: 无（在全部壁纸中轮换）");
+    } else {
+        println!("🏷️ 标签过滤: {}", active.join(", "));
+    }
+    EXIT_OK
+}
+
+/// 未设置 $EDITOR 时依次尝试的编辑器
+const FALLBACK_EDITORS: &[&str] = &["nano", "vi"];
+
+/// 用编辑器打开配置文件（不存在时先写入默认配置），退出后检查格式
+fn edit_config(path: Option<&std::path::Path>) -> i32 {
+    let path = path.map_or_else(Config::config_path, std::path::Path::to_path_buf);
+    if !path.exists() {
+        // load_from 在文件不存在时写入带注释的默认配置
+        if let Err(e) = Config::load_from(&path) {
+            eprintln!("❌ {}", e);
+            return EXIT_FAILURE;
+        }
+        println!("📝 已创建默认配置: {}", path.display());
+    }
+
+    // $EDITOR 可能带参数，如 "code --wait"
+    let editors: Vec<Vec<String>> = match std::env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => {
+            vec![editor.split_whitespace().map(str::to_string).collect()]
+        }
+        _ => FALLBACK_EDITORS
+            .iter()
+            .map(|e| vec![e.to_string()])
+            .collect(),
+    };
+
+    let status = editors.iter().find_map(|editor| {
+        let result = std::process::Command::new(&editor[0])
+            .args(&editor[1..])
+            .arg(&path)
+            .status();
+        match result {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            other => Some((editor[0].as_str(), other)),
+        }
+    });
+
+    match status {
+        None => {
+            eprintln!(
+                "❌ 找不到编辑器，请设置 $EDITOR（已尝试: {}）",
+                editors
+                    .iter()
+                    .map(|e| e[0].as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            return EXIT_FAILURE;
+        }
+        Some((editor, Err(e))) => {
+            eprintln!("❌ 无法启动编辑器 {}: {}", editor, e);
+            return EXIT_FAILURE;
+        }
+        Some((editor, Ok(status))) if !status.success() => {
+            warn!("编辑器 {} 异常退出: {}", editor, status);
+        }
+        Some(_) => {}
+    }
+
+    match Config::load_from(&path) {
+        Ok(_) => {
+            println!("✅ 配置文件格式正确: {}", path.display());
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// 输出配置文件原文与运行时解析后的配置
+fn show_config(config: &Config) -> i32 {
+    match &config.source_path {
+        Some(path) => {
+            println!("# ===== 配置文件: {} =====", path.display());
+            match std::fs::read_to_string(path) {
+                Ok(content) => println!("{}", content.trim_end()),
+                Err(e) => println!("# 无法读取: {}", e),
+            }
+        }
+        None => println!("# ===== 配置文件加载失败，使用默认配置 ====="),
+    }
+
+    println!();
+    println!("# ===== 合并默认值后的配置 =====");
+    match toml::to_string_pretty(config) {
+        Ok(content) => println!("{}", content.trim_end()),
+        Err(e) => {
+            eprintln!("❌ 序列化配置失败: {}", e);
+            return EXIT_FAILURE;
+        }
+    }
+
+    println!();
+    println!("# ===== 运行时解析结果 =====");
+    for (name, mode) in [
+        ("video", WallpaperMode::Video),
+        ("image", WallpaperMode::Image),
+    ] {
+        let dirs: Vec<String> = config
+            .all_wallpaper_dirs(mode)
+            .iter()
+            .map(|d| d.display().to_string())
+            .collect();
+        println!("[{}]", name);
+        println!("engine = {}", config.engine_type(mode));
+        println!("wallpaper_dir = {}", config.wallpaper_dir(mode).display());
+        println!("all_dirs = {}", dirs.join(", "));
+        println!("cache = {}", config.cache_path(mode).display());
+        println!(
+            "interval = {}s (±{}s)",
+            config.interval(mode),
+            config.interval_jitter(mode)
+        );
+        println!("per_monitor = {}", config.per_monitor(mode));
+        println!("watch = {}", config.watch(mode));
+        println!("tags = {}", config.active_tags(mode).join(", "));
+        println!();
+    }
+
+    let (low, recovery) = config.vram_thresholds();
+    println!("[runtime]");
+    println!("vram_thresholds = {}% / {}%", low, recovery);
+    if let Some(image) = config.low_vram_fallback_image() {
+        println!("low_vram_fallback_image = {}", image.display());
+    }
+    let outputs = monitor::list_outputs();
+    if outputs.is_empty() {
+        println!("outputs = (未检测到)");
+    } else {
+        println!("outputs = {}", outputs.join(", "));
+    }
+    println!("current_mode = {:?}", Config::load_current_mode());
+    println!("paused = {}", Config::is_paused());
+    if let Some(pinned) = Config::load_pinned() {
+        println!("pinned = {}", pinned.display());
+    }
+    EXIT_OK
+}
+
+/// 输出缓存检查结果：有失效记录且未修复时返回失败
+fn print_verify_report(report: &manager::VerifyReport) -> i32 {
+    println!(
+        "🔍 已检查 {} 条权重记录 ({})",
+        report.checked,
+        report.cache_path.display()
+    );
+    if !report.missing.is_empty() {
+        println!("❌ 文件已不存在 ({}):", report.missing.len());
+        for path in &report.missing {
+            println!("   {}", path.display());
+        }
+    }
+    if !report.unreadable.is_empty() {
+        println!("⚠️  无法读取 ({}):", report.unreadable.len());
+        for (path, reason) in &report.unreadable {
+            println!("   {} ({})", path.display(), reason);
+        }
+    }
+    if !report.untracked.is_empty() {
+        println!(
+            "➕ 缓存中没有记录，下次扫描时加入 ({}):",
+            report.untracked.len()
+        );
+        for path in &report.untracked {
+            println!("   {}", path.display());
+        }
+    }
+
+    if report.fixed {
+        println!(
+            "✅ 已移除 {} 条失效记录",
+            report.missing.len() + report.unreadable.len()
+        );
+        EXIT_OK
+    } else if report.has_stale() {
+        println!("💡 使用 --fix 从缓存中移除失效记录");
+        EXIT_FAILURE
+    } else {
+        println!("✅ 没有失效记录");
+        EXIT_OK
+    }
+}
+
+/// 列出内容相同的壁纸；remove 时标记副本，delete_files 时从磁盘删除副本
+fn dedup_wallpapers(manager: &mut WallManager, remove: bool, delete_files: bool) -> i32 {
+    let groups = manager.find_duplicates();
+    if groups.is_empty() {
+        println!("✅ 没有发现重复的壁纸");
+        return EXIT_OK;
+    }
+
+    let copies: usize = groups.iter().map(|g| g.len() - 1).sum();
+    println!("🔁 发现 {} 组重复壁纸，共 {} 个副本:", groups.len(), copies);
+    for group in &groups {
+        println!("  保留: {}", group[0].display());
+        for copy in &group[1..] {
+            println!("  副本: {}", copy.display());
+        }
+    }
+
+    if delete_files {
+        let mut failures: Vec<(PathBuf, String)> = Vec::new();
+        let mut deleted = 0;
+        for group in &groups {
+            let kept = &group[0];
+            for copy in &group[1..] {
+                // 内容标识只覆盖文件开头，删除前逐字节确认与保留的文件相同
+                match same_content(kept, copy) {
+                    Ok(true) => match std::fs::remove_file(copy) {
+                        Ok(()) => deleted += 1,
+                        Err(e) => failures.push((copy.clone(), e.to_string())),
+                    },
+                    Ok(false) => println!(
+                        "  ⚠️ 内容与 {} 不完全相同，未删除: {}",
+                        kept.display(),
+                        copy.display()
+                    ),
+                    Err(e) => failures.push((copy.clone(), format!("无法比较内容: {}", e))),
+                }
+            }
+        }
+        println!("🗑️ 已删除 {} 个副本文件", deleted);
+        // 重新扫描，从缓存中移除已删除的文件
+        manager.reset();
+        return report_failures(&failures);
+    }
+    if !remove {
+        println!("💡 使用 --remove 将副本标记为重复（不删除文件），轮换时只计一份");
+        return EXIT_OK;
+    }
+    match manager.mark_duplicates(&groups) {
+        Ok(marked) => {
+            println!("✅ 新标记 {} 个副本，轮换时不再选中（文件未删除）", marked);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("❌ 无法写入缓存文件: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// 打印批量操作的失败汇总，有失败项时返回 EXIT_FAILURE
+fn report_failures(failures: &[(PathBuf, String)]) -> i32 {
+    if failures.is_empty() {
+        return EXIT_OK;
+    }
+    eprintln!("❌ {} 个文件处理失败:", failures.len());
+    for (path, reason) in failures {
+        eprintln!("  {}: {}", path.display(), reason);
+    }
+    EXIT_FAILURE
+}
+
+/// 在 [-jitter, jitter] 范围内随机选取切换间隔的偏移量
+fn roll_interval_jitter(jitter: u64) -> i64 {
+    if jitter == 0 {
+        return 0;
+    }
+    let jitter = jitter.min(i64::MAX as u64) as i64;
+    rand::thread_rng().gen_range(-jitter..=jitter)
+}
+
+/// 手动调整壁纸权重（like / dislike）
+fn adjust_weight(config: Config, path: &str, like: bool) -> i32 {
+    let path = Config::expand_path(path);
+    let mode = config
+        .mode_for_path(&path)
+        .unwrap_or_else(Config::load_current_mode);
+    let delta = if like {
+        config.weight.feedback_delta
+    } else {
+        -config.weight.feedback_delta
+    };
+
+    let Some(mut manager) = open_manager(config, mode) else {
+        return EXIT_FAILURE;
+    };
+    match manager.adjust_weight(&path, delta) {
+        Ok(value) => {
+            let icon = if like { "👍" } else { "👎" };
+            println!("{} {} → 权重 {:.2}", icon, path.display(), value);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// 手动切换间隔不足时的提示
+fn switch_too_soon(wait: Duration) -> String {
+    format!(
+        "距上次切换太近，已忽略（{} 毫秒后可再次切换，见 min_switch_interval_ms）",
+        wait.as_millis().max(1)
+    )
+}
+
+/// 处理控制接口收到的命令，manager 为当前模式的壁纸管理器
+fn handle_request(request: &Request, manager: Option<&mut WallManager>) -> Reply {
+    let manager = match request {
+        Request::Pause => {
+            Config::save_paused(true);
+            return Reply::ok("⏸️ 已暂停自动轮换");
+        }
+        Request::Resume => {
+            Config::save_paused(false);
+            return Reply::ok("▶️ 已恢复自动轮换");
+        }
+        _ => match manager {
+            Some(manager) => manager,
+            None => return Reply::err("静态壁纸不可用"),
+        },
+    };
+
+    match request {
+        Request::Set(path) => {
+            if manager
+                .config
+                .mode_for_path(path)
+                .is_some_and(|m| m != manager.mode)
+            {
+                return Reply::err("该壁纸不属于当前模式，请先切换模式");
+            }
+            if let Err(wait) = manager.config.claim_switch(manager.mode) {
+                return Reply::err(switch_too_soon(wait));
+            }
+            match manager.show(path) {
+                Ok(()) => Reply::ok(format!("已显示: {}", path.display())),
+                Err(e) => Reply::err(e),
+            }
+        }
+        Request::Status => match manager.list_wallpapers(ListFormat::Table, SortKey::Value) {
+            Ok(list) => Reply::ok(format!("{}\n{}", manager.status(), list)),
+            Err(e) => Reply::err(e),
+        },
+        _ => {
+            if let Err(wait) = manager.config.claim_switch(manager.mode) {
+                return Reply::err(switch_too_soon(wait));
+            }
+            match manager.next() {
+                Ok(_) => Reply::ok(match manager.mode {
+                    WallpaperMode::Video => "✅ 动态壁纸切换成功",
+                    WallpaperMode::Image => "✅ 静态壁纸切换成功",
+                }),
+                Err(e) => Reply::err(format!("切换失败: {}", e)),
+            }
         }
     }
 }
 
 /// 运行守护进程（带显存监控）
-fn run_daemon(config: Config) {
-    let mut video_manager = WallManager::new(config.clone(), WallpaperMode::Video);
+///
+/// `once` 为 true 时只切换一次就返回退出码，否则永不返回
+fn run_daemon(config: Config, dry_run: bool, once: bool) -> i32 {
+    let opened = if dry_run {
+        open_dry_run_manager(config.clone(), WallpaperMode::Video)
+    } else {
+        open_engine_manager(config.clone(), WallpaperMode::Video)
+    };
+    let Some(mut video_manager) = opened else {
+        return EXIT_FAILURE;
+    };
     let mut image_manager: Option<WallManager> = None;
 
     let video_interval = config.interval(WallpaperMode::Video);
-    let vram_config = &config.vram;
+    // 演练模式下不做显存监控，避免停止正在运行的引擎
+    let vram_config = &VramConfig {
+        enabled: config.vram.enabled && !dry_run,
+        ..config.vram.clone()
+    };
+    let (vram_low_threshold, vram_recovery_threshold) = config.vram_thresholds();
 
     let mut vram_state = VramMonitorState::default();
+
+    // 显存不足时显示的备用图片，使用静态壁纸引擎显示
+    let low_vram_fallback = config.low_vram_fallback_image().and_then(|image| {
+        if image.is_file() {
+            match create_engine(config.engine_type(WallpaperMode::Image), &config) {
+                Ok(engine) => Some((image, engine)),
+                Err(e) => {
+                    warn!("无法显示降级备用图片: image_engine.type: {}", e);
+                    None
+                }
+            }
+        } else {
+            warn!("降级备用图片不存在，已忽略: {}", image.display());
+            None
+        }
+    });
+
     let mut last_switch = Instant::now();
+    let mut last_pin_check = Instant::now();
+    // 本轮切换间隔的随机偏移（interval_jitter_secs 为 0 时恒为 0）
+    let mut interval_offset = roll_interval_jitter(config.interval_jitter(WallpaperMode::Video));
 
     // 初始模式
     let mut current_mode = WallpaperMode::Video;
-    Config::save_current_mode(current_mode);
+    if !dry_run {
+        Config::save_current_mode(current_mode);
+    }
 
-    println!("🎬 LianWall 守护进程启动");
-    println!("引擎: {}", video_manager.engine.name());
-    println!("切换间隔: {}秒", video_interval);
-    println!("壁纸数量: {}", video_manager.wallpapers.len());
+    info!("🎬 LianWall 守护进程启动");
+    if dry_run {
+        info!("演练模式: 不会实际切换壁纸，也不会修改权重");
+    }
+    info!("引擎: {}", video_manager.engine.name());
+    info!("切换间隔: {}秒", video_interval);
+    info!("壁纸数量: {}", video_manager.wallpapers.len());
 
     if vram_config.enabled {
-        println!("显存监控: 已启用");
-        println!("  - 降级阈值: 剩余 < {}%", vram_config.threshold_percent);
-        println!("  - 恢复阈值: 剩余 > {}%", vram_config.recovery_percent);
-        println!("  - 检测间隔: {}秒", vram_config.check_interval);
+        info!("显存监控: 已启用");
+        info!("  - 降级阈值: 剩余 < {}%", vram_low_threshold);
+        info!("  - 恢复阈值: 剩余 > {}%", vram_recovery_threshold);
+        info!("  - 检测间隔: {}秒", vram_config.check_interval);
 
         // 打印当前显存状态
         if let Some(info) = get_vram_info() {
-            println!(
+            info!(
                 "  - 当前状态: {}/{} MB ({:.1}% 使用, {:.1}% 剩余)",
                 info.used_mb, info.total_mb, info.usage_percent, info.free_percent
             );
         } else {
-            println!("  ⚠️ 无法获取显存信息（可能不支持该显卡）");
+            warn!("  ⚠️ 无法获取显存信息（可能不支持该显卡）");
         }
     } else {
-        println!("显存监控: 已禁用");
+        info!("显存监控: 已禁用");
+    }
+    info!("---");
+
+    // 单次模式：暂停或固定壁纸时不切换
+    if once && (Config::is_paused() || Config::load_pinned().is_some()) {
+        info!("自动轮换已暂停或壁纸已固定，跳过本次切换");
+        return EXIT_OK;
     }
-    println!("---");
 
-    // 立即播放第一个壁纸
+    // 立即播放第一个壁纸，成功后通知 systemd 已就绪
+    let mut ready = false;
     match video_manager.next() {
-        Ok(_) => {}
-        Err(e) => eprintln!("初始壁纸切换失败: {}", e),
+        Ok(_) if once => return EXIT_OK,
+        Ok(_) => {
+            systemd::notify_ready();
+            ready = true;
+        }
+        Err(e) => {
+            error!("初始壁纸切换失败: {}", e);
+            if once {
+                return EXIT_FAILURE;
+            }
+        }
     }
 
+    // 收到 SIGTERM / SIGINT 时在下一个周期正常退出
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        if let Err(e) = signal_hook::flag::register(signal, Arc::clone(&shutdown)) {
+            warn!("无法注册信号处理: {}", e);
+        }
+    }
+    let mut watchdog = systemd::Watchdog::new();
+
+    // 目录监听（可选）
+    let watch_dirs = |mode: WallpaperMode| {
+        if config.watch(mode) {
+            DirWatcher::new(&config.all_wallpaper_dirs(mode))
+        } else {
+            None
+        }
+    };
+    let mut video_watcher = watch_dirs(WallpaperMode::Video);
+    let mut image_watcher = watch_dirs(WallpaperMode::Image);
+
+    // 控制接口（演练模式下不监听，避免 CLI 命令被转发给不会切换壁纸的进程）
+    let ipc = if dry_run { None } else { IpcServer::start() };
+
     loop {
-        thread::sleep(Duration::from_secs(1));
+        // 有控制接口时在等待命令的同时计时，命令到达后立即处理
+        let request = match &ipc {
+            Some(ipc) => ipc.wait(Duration::from_secs(1)),
+            None => {
+                thread::sleep(Duration::from_secs(1));
+                None
+            }
+        };
+
+        if shutdown.load(Ordering::Relaxed) {
+            info!("收到退出信号，守护进程退出");
+            systemd::notify_stopping();
+            video_manager.flush();
+            if let Some(ref img_mgr) = image_manager {
+                img_mgr.flush();
+            }
+            // 按配置停止正在显示壁纸的引擎（显存降级时为备用图片所用的引擎）
+            if config.stop_on_exit(current_mode) && !dry_run {
+                let stopped = if vram_state.fallback {
+                    low_vram_fallback.as_ref().map(|(_, engine)| engine.stop())
+                } else {
+                    match current_mode {
+                        WallpaperMode::Video => Some(video_manager.engine.stop()),
+                        WallpaperMode::Image => image_manager.as_ref().map(|m| m.engine.stop()),
+                    }
+                };
+                if let Some(Err(e)) = stopped {
+                    warn!("{}", e);
+                }
+            }
+            return EXIT_OK;
+        }
+        watchdog.ping();
+
+        if let Some((request, reply_tx)) = request {
+            let manager = match current_mode {
+                WallpaperMode::Video => Some(&mut video_manager),
+                WallpaperMode::Image => image_manager.as_mut(),
+            };
+            let reply = if vram_state.paused && matches!(request, Request::Next | Request::Set(_)) {
+                Reply::err("显存不足，壁纸轮换已暂停")
+            } else {
+                handle_request(&request, manager)
+            };
+            if reply.ok && matches!(request, Request::Next | Request::Set(_)) {
+                last_switch = Instant::now();
+            }
+            reply_tx.send(reply).ok();
+        }
 
         // 显存监控检查
         if vram_config.enabled
@@ -192,23 +1151,64 @@ fn run_daemon(config: Config) {
 
             if !vram_state.degraded {
                 // 当前是视频模式，检查是否需要降级
-                if is_vram_low(vram_config.threshold_percent) {
-                    println!("⚠️ 显存紧张！自动切换到静态壁纸模式");
+                if is_vram_low(vram_low_threshold) {
+                    warn!("⚠️ 显存紧张！自动切换到静态壁纸模式");
 
-                    // 初始化图片管理器（懒加载）
-                    if image_manager.is_none() {
-                        image_manager =
-                            Some(WallManager::new(config.clone(), WallpaperMode::Image));
-                    }
+                    // 配置了备用图片时直接显示它，暂停轮换直到显存恢复
+                    let fallback_shown =
+                        low_vram_fallback.as_ref().is_some_and(|(image, engine)| {
+                            match engine.set_wallpaper(image) {
+                                Ok(()) => {
+                                    engine.wait_until_shown(
+                                        std::slice::from_ref(image),
+                                        config.ready_timeout(),
+                                    );
+                                    let _ = std::process::Command::new("pkill")
+                                        .arg("mpvpaper")
+                                        .status();
+                                    info!("🖼️ 已显示降级备用图片: {}", image.display());
+                                    true
+                                }
+                                Err(e) => {
+                                    error!("显示降级备用图片失败: {}", e);
+                                    false
+                                }
+                            }
+                        });
 
-                    // 切换到图片模式
-                    if let Some(ref mut img_mgr) = image_manager {
-                        // 先设置静态壁纸
-                        if let Err(e) = img_mgr.next() {
-                            eprintln!("切换静态壁纸失败: {}", e);
+                    if fallback_shown {
+                        vram_state.degraded = true;
+                        vram_state.paused = true;
+                        vram_state.fallback = true;
+                    } else {
+                        // 初始化图片管理器（懒加载）
+                        if image_manager.is_none() {
+                            image_manager =
+                                WallManager::try_new(config.clone(), WallpaperMode::Image, false)
+                                    .and_then(|m| m.ensure_engine_available().map(|_| m))
+                                    .map(with_active_tags)
+                                    .map_err(|e| error!("无法初始化静态壁纸: {}", e))
+                                    .ok();
+                        }
+
+                        // 切换到图片模式：先设置静态壁纸
+                        let switched = match image_manager.as_mut() {
+                            Some(img_mgr) => img_mgr.next(),
+                            None => Err("静态壁纸不可用".to_string()),
+                        };
+                        if let Err(e) = switched {
+                            error!("切换静态壁纸失败: {}", e);
+
+                            // 没有可用的静态壁纸，直接暂停动态壁纸释放显存
+                            let _ = std::process::Command::new("pkill").arg("mpvpaper").status();
+                            vram_state.degraded = true;
+                            vram_state.paused = true;
+                            info!("⏸️ 已暂停动态壁纸，等待显存恢复");
                         } else {
-                            // 等待 swww 渲染
-                            thread::sleep(Duration::from_millis(500));
+                            // 等待静态壁纸显示后再停止 mpvpaper
+                            if let Some(img_mgr) = &image_manager {
+                                img_mgr.wait_until_shown(config.ready_timeout());
+                            }
                             // 停止 mpvpaper
                             let _ = std::process::Command::new("pkill").arg("mpvpaper").status();
 
@@ -218,33 +1218,47 @@ fn run_daemon(config: Config) {
                             last_switch = Instant::now();
 
                             if let Some(info) = get_vram_info() {
-                                println!("  显存: {:.1}% 剩余 → 已降级", info.free_percent);
+                                info!("  显存: {:.1}% 剩余 → 已降级", info.free_percent);
                             }
                         }
                     }
                 }
             } else {
                 // 当前是降级模式（图片），检查是否可以恢复
-                if is_vram_recovered(vram_config.recovery_percent) {
-                    println!("✅ 显存已恢复，切换回动态壁纸模式");
-
-                    // 停止 swww
-                    let _ = std::process::Command::new("swww")
-                        .arg("kill")
-                        .stderr(std::process::Stdio::null())
-                        .status();
-
-                    // 恢复视频模式
-                    if let Err(e) = video_manager.next() {
-                        eprintln!("恢复动态壁纸失败: {}", e);
+                if is_vram_recovered(vram_recovery_threshold) {
+                    info!("✅ 显存已恢复，切换回动态壁纸模式");
+
+                    // 停止备用图片或 swww（没有静态壁纸而暂停时没有启动过）
+                    if vram_state.fallback {
+                        if let Some((_, engine)) = &low_vram_fallback {
+                            let _ = engine.stop();
+                        }
+                    } else if !vram_state.paused {
+                        let _ = std::process::Command::new("swww")
+                            .arg("kill")
+                            .stderr(std::process::Stdio::null())
+                            .status();
+                    }
+
+                    // 恢复视频模式（固定的动态壁纸被降级覆盖时重新显示它）
+                    let restored = match Config::load_pinned() {
+                        Some(pin) if config.mode_for_path(&pin) == Some(WallpaperMode::Video) => {
+                            video_manager.show(&pin)
+                        }
+                        _ => video_manager.next(),
+                    };
+                    if let Err(e) = restored {
+                        error!("恢复动态壁纸失败: {}", e);
                     } else {
                         current_mode = WallpaperMode::Video;
                         Config::save_current_mode(current_mode);
                         vram_state.degraded = false;
+                        vram_state.paused = false;
+                        vram_state.fallback = false;
                         last_switch = Instant::now();
 
                         if let Some(info) = get_vram_info() {
-                            println!("  显存: {:.1}% 剩余 → 已恢复", info.free_percent);
+                            info!("  显存: {:.1}% 剩余 → 已恢复", info.free_percent);
                         }
                     }
                 }
@@ -252,28 +1266,93 @@ fn run_daemon(config: Config) {
         }
 
         // 壁纸切换逻辑
-        let interval = match current_mode {
-            WallpaperMode::Video => config.interval(WallpaperMode::Video),
-            WallpaperMode::Image => config.interval(WallpaperMode::Image),
+        let interval = config
+            .interval(current_mode)
+            .saturating_add_signed(interval_offset)
+            .max(1);
+
+        // 时间段变化时重新扫描对应目录，当前模式的目录变化后立即切换
+        let video_changed = video_manager.refresh_schedule();
+        let image_changed = image_manager
+            .as_mut()
+            .is_some_and(|img_mgr| img_mgr.refresh_schedule());
+        video_manager.refresh_tags();
+        if let Some(ref mut img_mgr) = image_manager {
+            img_mgr.refresh_tags();
+        }
+        let schedule_changed = match current_mode {
+            WallpaperMode::Video => video_changed,
+            WallpaperMode::Image => image_changed,
         };
 
-        if last_switch.elapsed() >= Duration::from_secs(interval) {
-            last_switch = Instant::now();
+        // 壁纸目录发生变化时自动重新扫描
+        if video_watcher.as_mut().is_some_and(DirWatcher::poll) {
+            video_manager.reset();
+        }
+        if image_watcher.as_mut().is_some_and(DirWatcher::poll)
+            && let Some(ref mut img_mgr) = image_manager
+        {
+            img_mgr.reset();
+        }
 
-            match current_mode {
-                WallpaperMode::Video => {
-                    if let Err(e) = video_manager.next() {
-                        eprintln!("切换动态壁纸失败: {}", e);
-                    }
-                }
-                WallpaperMode::Image => {
-                    if let Some(ref mut img_mgr) = image_manager {
-                        if let Err(e) = img_mgr.next() {
-                            eprintln!("切换静态壁纸失败: {}", e);
-                        }
-                    }
+        // 用户暂停或固定壁纸时不切换，恢复后重新计时
+        let pinned = Config::load_pinned();
+        if Config::is_paused() || pinned.is_some() {
+            last_switch = Instant::now();
+            // 固定的壁纸被其他引擎替换时重新显示（显存降级期间由恢复流程处理）
+            if let Some(pin) = pinned
+                && !dry_run
+                && !vram_state.degraded
+                && config
+                    .mode_for_path(&pin)
+                    .is_none_or(|mode| mode == current_mode)
+                && last_pin_check.elapsed() >= PIN_CHECK_INTERVAL
+            {
+                last_pin_check = Instant::now();
+                let reasserted = match current_mode {
+                    WallpaperMode::Video => video_manager.reassert_pinned(&pin),
+                    WallpaperMode::Image => image_manager
+                        .as_mut()
+                        .map_or(Ok(false), |img_mgr| img_mgr.reassert_pinned(&pin)),
+                };
+                if let Err(e) = reasserted {
+                    warn!("无法重新显示固定的壁纸: {}", e);
                 }
             }
+            continue;
+        }
+
+        if !vram_state.paused
+            && (schedule_changed || last_switch.elapsed() >= Duration::from_secs(interval))
+        {
+            last_switch = Instant::now();
+            interval_offset = roll_interval_jitter(config.interval_jitter(current_mode));
+            debug!(
+                "下一次切换间隔: {}秒",
+                config
+                    .interval(current_mode)
+                    .saturating_add_signed(interval_offset)
+                    .max(1)
+            );
+
+            let switched = match current_mode {
+                WallpaperMode::Video => video_manager
+                    .next()
+                    .map_err(|e| error!("切换动态壁纸失败: {}", e))
+                    .is_ok(),
+                WallpaperMode::Image => image_manager.as_mut().is_some_and(|img_mgr| {
+                    img_mgr
+                        .next()
+                        .map_err(|e| error!("切换静态壁纸失败: {}", e))
+                        .is_ok()
+                }),
+            };
+
+            // 初始切换失败时，在第一次成功切换后再通知 systemd 已就绪
+            if switched && !ready {
+                systemd::notify_ready();
+                ready = true;
+            }
         }
     }
 }
@@ -1,20 +1,279 @@
+use log::{debug, error, info, warn};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
 use walkdir::WalkDir;
 
+use crate::algorithm::selector::Stats;
 use crate::algorithm::{WallpaperSelector, WeightCalculator};
-use crate::config::{Config, WallpaperMode};
+use crate::config::{Config, SelectionStrategy, WallpaperMode};
+use crate::monitor;
+use crate::optimize::get_or_resize_image;
+use crate::palette;
 use crate::paperengine::{PaperEngine, create_engine, supported_extensions};
+use crate::vram::get_vram_info;
 
 /// 壁纸数据结构
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Wallpaper {
     pub path: PathBuf,
     pub value: f64,
     pub skip_streak: u32,
     pub last_played: Option<u64>,
+    /// 上次应用时间衰减的时间戳
+    #[serde(default)]
+    pub last_decayed: Option<u64>,
+    /// 累计播放次数
+    #[serde(default)]
+    pub play_count: u32,
+    /// 标签，完整扫描时从 sidecar 文件（`<文件名>.toml` 或 `<文件名>.tags`）重新读取
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 文件无法读取（空文件、权限错误等），不再被选中，reset 时清除
+    #[serde(default)]
+    pub broken: bool,
+    /// 内容标识（文件大小与前 1MB 的哈希），用于识别被移动或重命名的壁纸
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// 视频时长（秒），启用 max_video_seconds 时由 ffprobe 读取并缓存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f64>,
+    /// 被 `lianwall dedup --remove` 标记为此壁纸的副本，保留的壁纸仍在库中时不会被选中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+}
+
+/// 扫描索引：上次完整扫描的结果，目录未变化时代替完整遍历
+#[derive(Serialize, Deserialize)]
+struct ScanIndex {
+    /// 扫描目录与扫描参数，见 `WallManager::index_key`
+    key: String,
+    /// 遍历到的各级目录及其修改时间
+    dirs: Vec<(PathBuf, SystemTime)>,
+    files: Vec<IndexedFile>,
+}
+
+/// 扫描到的壁纸文件
+#[derive(Serialize, Deserialize)]
+struct IndexedFile {
+    path: PathBuf,
+    /// 加入壁纸库的时间，见 `added_time`
+    added: SystemTime,
+    /// sidecar 标签，完整扫描时读取
+    tags: Vec<String>,
+    /// sidecar 标签文件的修改时间，没有标签文件时为 None
+    ///
+    /// 原地编辑标签文件不会更新所在目录的修改时间，读取索引时据此重新读取标签
+    sidecar_modified: Option<SystemTime>,
+}
+
+/// 状态报告（用于 JSON 输出）
+#[derive(Serialize, Debug)]
+pub struct StatusReport<'a> {
+    pub mode: &'static str,
+    pub engine: &'static str,
+    pub interval: u64,
+    pub paused: bool,
+    pub pinned: Option<PathBuf>,
+    /// 当前壁纸及其权重百分位（0.0 最低，1.0 最高）
+    pub current: Option<PathBuf>,
+    pub current_percentile: Option<f64>,
+    pub stats: Stats,
+    pub wallpapers: &'a [Wallpaper],
+}
+
+/// 缓存文件格式版本
+///
+/// - 0：早期版本，直接保存壁纸数组
+/// - 1：带版本号的 {"version", "wallpapers"} 结构
+const CACHE_VERSION: u32 = 1;
+
+/// 扫描索引格式版本，写入 `WallManager::index_key`，格式变化后旧索引失效
+///
+/// - 2：记录 sidecar 标签文件的修改时间
+const SCAN_INDEX_VERSION: u32 = 2;
+
+/// 缓存文件结构
+#[derive(Serialize, Deserialize)]
+struct CacheFile<W> {
+    version: u32,
+    wallpapers: Vec<W>,
+    /// sequential 策略上一次播放的壁纸，下次从排序后位于其后的壁纸继续
+    ///
+    /// 保存路径而不是序号，增删文件后按路径重新定位，不会跳过或重复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<PathBuf>,
+}
+
+/// 兼容新旧两种缓存格式
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CacheContent {
+    Versioned(CacheFile<Wallpaper>),
+    Legacy(Vec<Wallpaper>),
+}
+
+/// 解析缓存文件内容，旧格式（纯列表）的版本号为 0
+fn parse_cache(content: &str) -> serde_json::Result<CacheFile<Wallpaper>> {
+    Ok(match serde_json::from_str(content)? {
+        CacheContent::Versioned(cache) => cache,
+        CacheContent::Legacy(wallpapers) => CacheFile {
+            version: 0,
+            wallpapers,
+            cursor: None,
+        },
+    })
+}
+
+/// 缓存完整性检查结果，见 `verify_cache`
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// 缓存文件路径
+    pub cache_path: PathBuf,
+    /// 检查的权重记录数
+    pub checked: usize,
+    /// 文件已不存在的记录
+    pub missing: Vec<PathBuf>,
+    /// 文件存在但无法读取的记录（附带原因）
+    pub unreadable: Vec<(PathBuf, String)>,
+    /// 壁纸目录中存在但缓存中没有记录的文件（下次扫描时自动加入）
+    pub untracked: Vec<PathBuf>,
+    /// 是否已移除失效记录并写回缓存
+    pub fixed: bool,
+}
+
+impl VerifyReport {
+    /// 是否存在失效记录
+    pub fn has_stale(&self) -> bool {
+        !self.missing.is_empty() || !self.unreadable.is_empty()
+    }
+}
+
+/// 对照文件系统检查权重缓存
+///
+/// 直接读取缓存文件，不经过扫描合并（扫描会改写缓存）；检查每条记录的文件是否存在且可读，
+/// 并列出各时间段壁纸目录中没有记录的文件。fix 为 true 时移除失效记录并写回缓存
+pub fn verify_cache(
+    config: &Config,
+    mode: WallpaperMode,
+    fix: bool,
+) -> Result<VerifyReport, String> {
+    let cache_path = config.cache_path(mode);
+    let (wallpapers, cursor) = match fs::read_to_string(&cache_path) {
+        Ok(content) => {
+            let cache = parse_cache(&content)
+                .map_err(|e| format!("无法解析 {}: {}", cache_path.display(), e))?;
+            (cache.wallpapers, cache.cursor)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (Vec::new(), None),
+        Err(e) => return Err(format!("无法读取 {}: {}", cache_path.display(), e)),
+    };
+
+    let mut report = VerifyReport {
+        checked: wallpapers.len(),
+        ..Default::default()
+    };
+    let tracked: HashSet<PathBuf> = wallpapers.iter().map(|w| w.path.clone()).collect();
+    let mut valid = Vec::new();
+    for wall in wallpapers {
+        if !wall.path.exists() {
+            report.missing.push(wall.path);
+            continue;
+        }
+        match probe_file(&wall.path) {
+            Ok(()) => valid.push(wall),
+            Err(reason) => report.unreadable.push((wall.path, reason)),
+        }
+    }
+
+    let extensions = scan_extensions(config, mode)?;
+    let exclude = config.exclude_patterns();
+    for dir in config.all_wallpaper_dirs(mode) {
+        let (_, files) = walk_wallpapers(
+            &dir,
+            &extensions,
+            &exclude,
+            config.paths.follow_links,
+            config.paths.skip_hidden,
+        );
+        report
+            .untracked
+            .extend(files.into_iter().filter(|path| !tracked.contains(path)));
+    }
+    report.untracked.sort();
+    report.untracked.dedup();
+
+    if fix && report.has_stale() {
+        let cache = CacheFile {
+            version: CACHE_VERSION,
+            wallpapers: valid,
+            cursor,
+        };
+        let content = serde_json::to_string_pretty(&cache).map_err(|e| e.to_string())?;
+        write_atomic(&cache_path, content.as_bytes())
+            .map_err(|e| format!("无法写入 {}: {}", cache_path.display(), e))?;
+        report.fixed = true;
+    }
+    report.cache_path = cache_path;
+    Ok(report)
+}
+
+/// 单个文件读取元数据的超时时间
+const METADATA_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 单个文件计算内容标识（读取前 1MB）的超时时间
+const CONTENT_HASH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 引擎无法显示选中的壁纸时，一次切换中最多尝试的壁纸数
+const MAX_SWITCH_ATTEMPTS: usize = 3;
+
+/// prefer_new_files 启用时，新壁纸的初始权重至少比平均权重高出的选中惩罚次数
+const NEW_FILE_BOOST_ROUNDS: f64 = 3.0;
+
+/// 壁纸列表输出格式
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum ListFormat {
+    /// 带中文标签的表格（默认）
+    #[default]
+    Table,
+    /// CSV：path,value,skip_streak,last_played,play_count,tags
+    Csv,
+    /// JSON 数组
+    Json,
+}
+
+/// 壁纸列表排序方式
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortKey {
+    /// 按权重从高到低（默认）
+    #[default]
+    Value,
+    /// 按连续跳过次数从多到少（找出长期不出现的壁纸）
+    Skips,
+    /// 按文件名
+    Name,
+    /// 按上次播放时间从近到远，从未播放的排在最后
+    Recent,
+}
+
+impl SortKey {
+    /// 比较两张壁纸，相同时按权重从高到低
+    fn compare(self, a: &Wallpaper, b: &Wallpaper) -> Ordering {
+        let by_value = b.value.total_cmp(&a.value);
+        match self {
+            SortKey::Value => by_value,
+            SortKey::Skips => b.skip_streak.cmp(&a.skip_streak).then(by_value),
+            SortKey::Name => a.path.file_name().cmp(&b.path.file_name()).then(by_value),
+            SortKey::Recent => b.last_played.cmp(&a.last_played).then(by_value),
+        }
+    }
 }
 
 /// 壁纸管理器
@@ -24,90 +283,247 @@ pub struct WallManager {
     pub wallpapers: Vec<Wallpaper>,
     pub engine: Box<dyn PaperEngine>,
     weight_calc: WeightCalculator,
+    /// 上一次播放的壁纸，避免连续两次选中同一张
+    last_path: Option<PathBuf>,
+    /// 本次运行中最近播放的壁纸（最多 weight.session_no_repeat 个，不写入缓存）
+    recent: VecDeque<PathBuf>,
+    /// sequential 策略的播放位置（上一次按顺序播放的壁纸），随缓存保存
+    cursor: Option<PathBuf>,
+    /// 演练模式：只显示将要选择的壁纸，不实际设置，也不写入缓存和扫描索引
+    dry_run: bool,
+    /// 标签过滤：只在带有其中任一标签的壁纸中选择，为空表示不限制
+    pub tag_filter: Vec<String>,
+    /// 当前扫描的壁纸目录（启用时间段配置时会随时间变化）
+    scan_dir: PathBuf,
+    /// 其他时间段目录中的壁纸以及超过 max_video_seconds 的视频，不参与选择，但保留在缓存中
+    dormant: Vec<Wallpaper>,
+    /// 加载时缓存文件的格式版本
+    cache_version: u32,
+    /// 缓存文件存在但读取失败（权限、I/O 错误等），本次运行不写入，避免用重新初始化的权重覆盖它
+    cache_unreadable: bool,
+    /// 按扩展名路由的其他引擎（extension_engine 以及交给 swww 的 GIF），按引擎类型索引
+    routed_engines: HashMap<String, Box<dyn PaperEngine>>,
+    /// GIF 是否交给 swww 显示（仅当本模式不是 swww 而静态壁纸引擎为 swww 时）
+    gif_to_swww: bool,
+    /// 当前壁纸由哪个路由引擎显示，None 表示本模式的默认引擎
+    routed_active: Option<String>,
+    /// 静态壁纸预缩放的目标分辨率（未启用或检测失败时为 None）
+    screen_size: Option<(u32, u32)>,
+    /// 最近一次交给引擎显示的文件（预缩放后的路径，多显示器时每个输出一个）
+    shown: Vec<PathBuf>,
+    /// 扫描的文件扩展名（引擎支持的扩展名加上 extension_engine 中的扩展名）
+    extensions: Vec<String>,
 }
 
 impl WallManager {
     /// 初始化壁纸管理器
-    pub fn new(config: Config, mode: WallpaperMode) -> Self {
+    ///
+    /// dry_run 为 true 时为演练模式，加载和扫描都不会写入任何文件；
+    /// 配置的引擎类型无效时返回错误
+    pub fn try_new(config: Config, mode: WallpaperMode, dry_run: bool) -> Result<Self, String> {
         let engine_type = config.engine_type(mode);
-        let engine = create_engine(engine_type);
+        let engine = create_engine(engine_type, &config)
+            .map_err(|e| format!("{}: {}", engine_key(mode), e))?;
+        let extensions = scan_extensions(&config, mode)?;
 
         let weight_calc = WeightCalculator::new(config.weight.clone());
 
+        // GIF 同时被 mpvpaper 和 swww 支持：静态壁纸引擎为 swww 时，
+        // 动态壁纸目录中的 GIF 也交给 swww 原生播放，不再启动 mpv
+        let gif_to_swww = engine_type != "swww" && config.image_engine.engine_type == "swww";
+        let mut routed_engines = HashMap::new();
+        if gif_to_swww {
+            routed_engines.insert("swww".to_string(), create_engine("swww", &config)?);
+        }
+
+        // 按扩展名指定的引擎，与本模式默认引擎相同的映射只用于扫描
+        for (ext, routed) in config.extension_engines() {
+            let engine = create_engine(routed, &config)
+                .map_err(|e| format!("extension_engine.{}: {}", ext, e))?;
+            if routed != engine_type {
+                routed_engines.entry(routed.to_string()).or_insert(engine);
+            }
+        }
+        // 运行时标签（`lianwall tag`）属于用户状态，不在这里读取，由调用方另行设置（见 refresh_tags）
+        let tag_filter = config.configured_tags(mode);
+
+        let screen_size = if mode == WallpaperMode::Image && config.image_optimization.enabled {
+            let size = monitor::max_resolution();
+            if size.is_none() {
+                warn!("无法检测屏幕分辨率，静态壁纸预缩放不生效");
+            }
+            size
+        } else {
+            None
+        };
+
         let mut manager = Self {
             config,
             mode,
             wallpapers: Vec::new(),
             engine,
             weight_calc,
+            last_path: None,
+            recent: VecDeque::new(),
+            cursor: None,
+            dry_run,
+            tag_filter,
+            scan_dir: PathBuf::new(),
+            dormant: Vec::new(),
+            cache_version: CACHE_VERSION,
+            cache_unreadable: false,
+            routed_engines,
+            gif_to_swww,
+            routed_active: None,
+            shown: Vec::new(),
+            screen_size,
+            extensions,
         };
 
-        manager.load_and_scan();
-        manager
+        manager.load_and_scan(false);
+        Ok(manager)
+    }
+
+    /// 检查引擎程序是否已安装，未安装时返回可操作的错误信息
+    ///
+    /// 只有需要实际切换壁纸的命令才需要检查，status 等命令不依赖引擎程序
+    pub fn ensure_engine_available(&self) -> Result<(), String> {
+        if self.engine.is_available() {
+            return Ok(());
+        }
+        let name = self.engine.name();
+        Err(format!(
+            "{} 未在 PATH 中找到，请安装 {} 或修改 {}",
+            name,
+            name,
+            engine_key(self.mode)
+        ))
     }
 
     /// 加载缓存文件并扫描目录，合并权重
-    fn load_and_scan(&mut self) {
+    ///
+    /// full_walk 为 false 时，目录未变化则使用扫描索引代替完整遍历
+    fn load_and_scan(&mut self, full_walk: bool) {
         let cache_path = self.config.cache_path(self.mode);
         let scan_dir = self.config.wallpaper_dir(self.mode);
-        let engine_type = self.config.engine_type(self.mode);
-
-        let extensions = supported_extensions(engine_type);
+        self.scan_dir = scan_dir.clone();
+        let exclude = self.config.exclude_patterns();
 
-        let cached: Vec<Wallpaper> = if cache_path.exists() {
-            let content = fs::read_to_string(&cache_path).unwrap_or_default();
-            serde_json::from_str(&content).unwrap_or_default()
-        } else {
-            Vec::new()
+        self.cursor = None;
+        self.cache_unreadable = false;
+        let cached: Vec<Wallpaper> = match fs::read(&cache_path) {
+            Ok(content) => {
+                // 读取成功但内容无效（包括非 UTF-8）才视为损坏
+                let parsed = String::from_utf8(content)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| parse_cache(&content).map_err(|e| e.to_string()))
+                    .map(|cache| {
+                        self.cache_version = cache.version;
+                        self.cursor = cache.cursor;
+                        cache.wallpapers
+                    });
+                parsed.unwrap_or_else(|e| {
+                    if self.dry_run {
+                        warn!("缓存文件 {} 已损坏 ({})", cache_path.display(), e);
+                        return Vec::new();
+                    }
+                    // 缓存损坏时先备份，避免权重历史被静默丢弃
+                    let backup = path_with_suffix(&cache_path, ".bak");
+                    match fs::copy(&cache_path, &backup) {
+                        Ok(_) => warn!(
+                            "缓存文件 {} 已损坏 ({})，已备份到 {}，权重将重新初始化",
+                            cache_path.display(),
+                            e,
+                            backup.display()
+                        ),
+                        Err(copy_err) => warn!(
+                            "缓存文件 {} 已损坏 ({})，且备份失败: {}",
+                            cache_path.display(),
+                            e,
+                            copy_err
+                        ),
+                    }
+                    Vec::new()
+                })
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.cache_version = CACHE_VERSION;
+                Vec::new()
+            }
+            Err(e) => {
+                warn!(
+                    "无法读取缓存文件 {} ({})，本次运行使用初始权重且不写入缓存",
+                    cache_path.display(),
+                    e
+                );
+                self.cache_version = CACHE_VERSION;
+                self.cache_unreadable = true;
+                Vec::new()
+            }
         };
 
-        let cached_map: std::collections::HashMap<PathBuf, Wallpaper> =
-            cached.into_iter().map(|w| (w.path.clone(), w)).collect();
+        // 被排除的文件同时从缓存中移除
+        let cached: Vec<Wallpaper> = cached
+            .into_iter()
+            .filter(|w| !is_excluded(&w.path, &exclude))
+            .collect();
 
-        let mut scanned_files: Vec<(PathBuf, SystemTime)> = Vec::new();
+        let (cached, dormant): (Vec<Wallpaper>, Vec<Wallpaper>) = cached
+            .into_iter()
+            .partition(|w| w.path.starts_with(&scan_dir));
 
-        for entry in WalkDir::new(&scan_dir)
-            .follow_links(true)
+        // 其他时间段目录中仍存在的壁纸保留权重，切换回来时继续使用
+        let other_dirs: Vec<PathBuf> = self
+            .config
+            .all_wallpaper_dirs(self.mode)
             .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    let ext_lower = ext.to_string_lossy().to_lowercase();
-                    if extensions.iter().any(|&e| e == ext_lower) {
-                        let mtime = fs::metadata(path)
-                            .and_then(|m| m.modified())
-                            .unwrap_or(SystemTime::UNIX_EPOCH);
-                        scanned_files.push((path.to_path_buf(), mtime));
-                    }
-                }
-            }
+            .filter(|d| *d != scan_dir)
+            .collect();
+        // 其余条目的文件已被移动或删除（读取超时的视为仍存在，保留在 dormant 中）
+        let (dormant, gone): (Vec<Wallpaper>, Vec<Wallpaper>) =
+            dormant.into_iter().partition(|w| {
+                other_dirs.iter().any(|d| w.path.starts_with(d)) && !is_missing(&w.path)
+            });
+        self.dormant = dormant;
+
+        let cached_map: std::collections::HashMap<PathBuf, Wallpaper> =
+            cached.into_iter().map(|w| (w.path.clone(), w)).collect();
+
+        let scanned_files = if full_walk {
+            None
+        } else {
+            self.load_index(&scan_dir)
         }
+        .unwrap_or_else(|| self.walk_dir(&scan_dir));
+
+        // 扫描中已不存在的缓存条目按内容标识索引，新路径上内容相同的文件继承其权重
+        let scanned_paths: HashSet<&Path> =
+            scanned_files.iter().map(|f| f.path.as_path()).collect();
+        let mut moved: HashMap<String, Wallpaper> = cached_map
+            .values()
+            .filter(|w| !scanned_paths.contains(w.path.as_path()))
+            .chain(&gone)
+            .filter_map(|w| Some((w.content_hash.clone()?, w.clone())))
+            .collect();
 
         if scanned_files.is_empty() {
             let mode_str = match self.mode {
                 WallpaperMode::Video => "动态壁纸",
                 WallpaperMode::Image => "静态壁纸",
             };
-            println!(
-                "警告: 在 {} 中未找到支持的{}文件 ({})",
+            warn!(
+                "在 {} 中未找到支持的{}文件 ({})",
                 scan_dir.display(),
                 mode_str,
-                extensions.join(", ")
+                self.extensions.join(", ")
             );
+            self.wallpapers.clear();
             return;
         }
 
-        let (oldest, newest) = scanned_files.iter().fold(
-            (SystemTime::UNIX_EPOCH, SystemTime::now()),
-            |(oldest, newest), (_, mtime)| {
-                (
-                    if *mtime < oldest { oldest } else { *mtime },
-                    if *mtime > newest { newest } else { *mtime },
-                )
-            },
-        );
+        let times = || scanned_files.iter().map(|f| f.added);
+        let oldest = times().min().unwrap_or(SystemTime::UNIX_EPOCH);
+        let newest = times().max().unwrap_or(SystemTime::UNIX_EPOCH);
 
         let time_range = newest
             .duration_since(oldest)
@@ -124,48 +540,449 @@ impl WallManager {
 
         self.wallpapers = scanned_files
             .into_iter()
-            .map(|(path, mtime)| {
-                if let Some(cached_wallpaper) = cached_map.get(&path) {
+            .map(|file| {
+                let IndexedFile {
+                    path, added, tags, ..
+                } = file;
+                // 只有存在被移动的候选时才读取新文件的内容标识
+                let cached_wallpaper = cached_map.get(&path);
+                let content_hash = match cached_wallpaper {
+                    Some(cached_wallpaper) => cached_wallpaper.content_hash.clone(),
+                    None if !moved.is_empty() => content_hash_with_timeout(&path),
+                    None => None,
+                };
+                let mut wallpaper = if let Some(cached_wallpaper) = cached_wallpaper {
                     cached_wallpaper.clone()
+                } else if let Some(previous) =
+                    content_hash.as_ref().and_then(|hash| moved.remove(hash))
+                {
+                    info!(
+                        "识别到移动的壁纸，保留权重: {} → {}",
+                        previous.path.display(),
+                        path.display()
+                    );
+                    Wallpaper {
+                        path: path.clone(),
+                        ..previous
+                    }
                 } else {
                     let file_age = newest
-                        .duration_since(mtime)
+                        .duration_since(added)
                         .map(|d| d.as_secs_f64())
                         .unwrap_or(0.0);
                     let age_ratio = file_age / time_range;
 
                     let time_based_weight = self.weight_calc.calculate_initial_weight(age_ratio);
-                    let initial_value = (avg_value + time_based_weight) / 2.0;
+                    let mut initial_value = (avg_value + time_based_weight) / 2.0;
+
+                    // 已有权重历史时新加入的壁纸高于平均权重，在接下来几轮中优先出现
+                    // （首次扫描时所有壁纸都是新的，不做提升）
+                    if self.config.weight.prefer_new_files && !cached_map.is_empty() {
+                        initial_value = initial_value.max(
+                            avg_value + NEW_FILE_BOOST_ROUNDS * self.config.weight.select_penalty,
+                        );
+                    }
 
                     Wallpaper {
                         path,
                         value: initial_value,
-                        skip_streak: 0,
-                        last_played: None,
+                        ..Default::default()
                     }
-                }
+                };
+                wallpaper.tags = tags;
+                wallpaper.content_hash = content_hash;
+                wallpaper
+            })
+            .collect();
+        self.skip_long_videos();
+
+        self.weight_calc
+            .apply_decay(&mut self.wallpapers, unix_now());
+
+        self.last_path = self
+            .wallpapers
+            .iter()
+            .filter(|w| w.last_played.is_some())
+            .max_by_key(|w| w.last_played)
+            .map(|w| w.path.clone());
+
+        // 演练模式下衰减、新加入的壁纸和格式升级都只保留在内存中
+        if self.dry_run {
+            return;
+        }
+        if self.cache_version < CACHE_VERSION {
+            self.migrate_cache();
+        } else if let Err(e) = self.save() {
+            warn!("无法写入缓存文件，本次权重变化未保存: {}", e);
+        }
+    }
+
+    /// 扫描索引文件路径（与权重缓存放在一起，例如 video.json.index）
+    fn index_path(&self) -> PathBuf {
+        path_with_suffix(&self.config.cache_path(self.mode), ".index")
+    }
+
+    /// 扫描目录与扫描参数的标识，任一变化时索引失效
+    fn index_key(&self, scan_dir: &Path) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|{}",
+            SCAN_INDEX_VERSION,
+            scan_dir.display(),
+            self.extensions.join(","),
+            self.config.paths.exclude.join(","),
+            self.config.paths.follow_links,
+            self.config.paths.skip_hidden
+        )
+    }
+
+    /// 目录未变化时从扫描索引读取文件列表
+    ///
+    /// 增删或重命名文件会更新所在目录的修改时间，因此只比较索引中各级目录的修改时间，
+    /// 不逐个读取文件元数据；子目录中的变化不会更新顶层目录，所以每一级目录都要检查。
+    /// 原地编辑标签文件不会更新目录，有标签文件的条目单独比较标签文件的修改时间。
+    /// 索引不存在、参数不一致或任一目录有变化时返回 None
+    fn load_index(&self, scan_dir: &Path) -> Option<Vec<IndexedFile>> {
+        let content = fs::read_to_string(self.index_path()).ok()?;
+        let mut index: ScanIndex = serde_json::from_str(&content).ok()?;
+        if index.key != self.index_key(scan_dir) {
+            return None;
+        }
+
+        let changed = index.dirs.iter().find(|(dir, mtime)| {
+            metadata_with_timeout(dir, METADATA_TIMEOUT).and_then(|m| m.modified().ok())
+                != Some(*mtime)
+        });
+        if let Some((dir, _)) = changed {
+            debug!("目录已变化，完整扫描: {}", dir.display());
+            return None;
+        }
+
+        let mut tags_changed = false;
+        for file in index
+            .files
+            .iter_mut()
+            .filter(|f| f.sidecar_modified.is_some())
+        {
+            let modified = sidecar_modified(&file.path);
+            if modified != file.sidecar_modified {
+                debug!("标签文件已修改，重新读取: {}", file.path.display());
+                file.tags = read_sidecar_tags(&file.path);
+                file.sidecar_modified = modified;
+                tags_changed = true;
+            }
+        }
+        if tags_changed {
+            self.save_index(&index);
+        }
+
+        debug!("目录未变化，使用扫描索引（{} 个文件）", index.files.len());
+        Some(index.files)
+    }
+
+    /// 完整遍历壁纸目录，并将结果写入扫描索引（演练模式下不写入）
+    fn walk_dir(&self, scan_dir: &Path) -> Vec<IndexedFile> {
+        let (walked_dirs, paths) = walk_wallpapers(
+            scan_dir,
+            &self.extensions,
+            &self.config.exclude_patterns(),
+            self.config.paths.follow_links,
+            self.config.paths.skip_hidden,
+        );
+        // 有目录读不到修改时间时无法判断其是否变化，不写入索引
+        let indexable = walked_dirs.iter().all(|(_, mtime)| mtime.is_some());
+        let dirs = walked_dirs
+            .into_iter()
+            .filter_map(|(dir, mtime)| Some((dir, mtime?)))
+            .collect();
+
+        // 读取失败或超时（如网络存储离线）时跳过该文件，避免阻塞
+        let files = paths
+            .into_iter()
+            .filter_map(|path| {
+                let metadata = metadata_with_timeout(&path, METADATA_TIMEOUT)?;
+                Some(IndexedFile {
+                    added: added_time(&metadata),
+                    sidecar_modified: sidecar_modified(&path),
+                    tags: read_sidecar_tags(&path),
+                    path,
+                })
             })
             .collect();
 
-        self.save();
+        let index = ScanIndex {
+            key: self.index_key(scan_dir),
+            dirs,
+            files,
+        };
+        if self.dry_run {
+            return index.files;
+        }
+        // 目录不存在时没有可比较的修改时间，同样不写入索引
+        if !indexable || index.dirs.is_empty() {
+            fs::remove_file(self.index_path()).ok();
+        } else {
+            self.save_index(&index);
+        }
+        index.files
+    }
+
+    /// 写入扫描索引（演练模式下不写入）
+    fn save_index(&self, index: &ScanIndex) {
+        if self.dry_run {
+            return;
+        }
+        let index_path = self.index_path();
+        if let Err(e) = index_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| serde_json::to_string(index).map_err(io::Error::other))
+            .and_then(|content| write_atomic(&index_path, content.as_bytes()))
+        {
+            debug!("无法写入扫描索引 {}: {}", index_path.display(), e);
+        }
+    }
+
+    /// 将时长超过 max_video_seconds 的视频移出壁纸库
+    ///
+    /// 时长缓存在壁纸条目中，被移出的条目放入 dormant 随缓存一起保存，之后扫描不再重复读取
+    fn skip_long_videos(&mut self) {
+        let max_secs = self.config.max_video_seconds(self.mode);
+        if max_secs == 0 {
+            return;
+        }
+
+        let unprobed = self
+            .wallpapers
+            .iter()
+            .filter(|w| w.duration_secs.is_none())
+            .count();
+        if unprobed > 0 {
+            debug!("读取 {} 个视频的时长", unprobed);
+        }
+        for wall in self
+            .wallpapers
+            .iter_mut()
+            .filter(|w| w.duration_secs.is_none())
+        {
+            wall.duration_secs = probe_duration(&wall.path);
+        }
+
+        let (kept, too_long): (Vec<Wallpaper>, Vec<Wallpaper>) =
+            std::mem::take(&mut self.wallpapers)
+                .into_iter()
+                .partition(|w| w.duration_secs.is_none_or(|d| d <= max_secs as f64));
+        for wall in &too_long {
+            info!(
+                "跳过时长超过 {} 秒的视频: {} ({:.0} 秒)",
+                max_secs,
+                wall.path.display(),
+                wall.duration_secs.unwrap_or_default()
+            );
+        }
+        self.wallpapers = kept;
+        self.dormant.extend(too_long);
     }
 
     /// 选择下一张壁纸
     pub fn pick_next(&mut self) -> Option<Wallpaper> {
-        if self.wallpapers.is_empty() {
+        self.pick_next_excluding(&[])
+    }
+
+    /// 选择下一张可读的壁纸，跳过 taken 中的壁纸（用于多显示器时避免重复）
+    ///
+    /// 选中的文件损坏时标记后重新选择
+    fn pick_next_excluding(&mut self, taken: &[PathBuf]) -> Option<Wallpaper> {
+        loop {
+            let wallpaper = self.pick_candidate(taken)?;
+            match probe_file(&wallpaper.path) {
+                Ok(()) => return Some(wallpaper),
+                Err(e) => self.mark_broken(&wallpaper.path, &e),
+            }
+        }
+    }
+
+    /// 按权重选择候选壁纸，排除损坏、重复、不匹配标签和 taken 中的壁纸
+    fn pick_candidate(&mut self, taken: &[PathBuf]) -> Option<Wallpaper> {
+        // 标签过滤只限制候选范围，权重仍在整个壁纸库中更新
+        let kept: HashSet<&Path> = self
+            .wallpapers
+            .iter()
+            .filter(|w| !w.broken)
+            .map(|w| w.path.as_path())
+            .collect();
+        let tagged: Vec<Wallpaper> = self
+            .tagged_wallpapers()
+            .into_iter()
+            .filter(|w| !w.broken)
+            .filter(|w| !w.duplicate_of.as_deref().is_some_and(|p| kept.contains(p)))
+            .collect();
+        if tagged.is_empty() {
             return None;
         }
 
+        // 排除冷却中、本次运行中刚播放过和已占用的壁纸，候选为空时逐步放宽限制
+        let now = unix_now();
+        let available = |w: &&Wallpaper| !taken.contains(&w.path);
+        let not_recent = |w: &&Wallpaper| !self.recent.contains(&w.path);
+        let mut pool: Vec<Wallpaper> = tagged
+            .iter()
+            .filter(available)
+            .filter(not_recent)
+            .filter(|w| self.cooldown_remaining(w, now).is_none())
+            .cloned()
+            .collect();
+        if pool.is_empty() {
+            pool = tagged
+                .iter()
+                .filter(available)
+                .filter(not_recent)
+                .cloned()
+                .collect();
+        }
+        if pool.is_empty() {
+            pool = tagged.iter().filter(available).cloned().collect();
+        }
+        if pool.is_empty() {
+            pool = tagged.clone();
+        }
+
+        debug!(
+            "候选壁纸 {} / {}（已排除冷却中、最近播放、已占用、损坏与不匹配标签的壁纸）",
+            pool.len(),
+            self.wallpapers.len()
+        );
+
+        match self.config.weight.selection_strategy {
+            SelectionStrategy::Weighted => {}
+            SelectionStrategy::Shuffle => {
+                if pool.len() > 1 {
+                    pool.retain(|w| Some(w.path.as_path()) != self.last_path.as_deref());
+                }
+                let idx = rand::thread_rng().gen_range(0..pool.len());
+                return Some(pool.swap_remove(idx));
+            }
+            SelectionStrategy::Sequential => {
+                pool.sort_by(|a, b| a.path.cmp(&b.path));
+                let idx = self
+                    .cursor
+                    .as_deref()
+                    .and_then(|last| pool.iter().position(|w| w.path.as_path() > last))
+                    .unwrap_or(0);
+                return Some(pool.swap_remove(idx));
+            }
+        }
+
+        if let Some(factor) = self.size_penalty_factor() {
+            apply_size_penalty(&mut pool, factor, |path| {
+                fs::metadata(path).ok().map(|m| m.len())
+            });
+        }
+
         let perturbation_ratio = self.config.weight.perturbation_ratio;
-        let idx = WallpaperSelector::select(&mut self.wallpapers, 5.0, perturbation_ratio)?;
-        let selected = self.wallpapers[idx].clone();
+        let tolerance = self.config.weight.selection_tolerance;
+        let idx = WallpaperSelector::select(
+            &mut pool,
+            tolerance,
+            perturbation_ratio,
+            self.last_path.as_deref(),
+        )?;
+
+        Some(pool.swap_remove(idx))
+    }
+
+    /// 显存紧张时按文件大小扣除候选权重的系数，未启用或显存充足时为 None
+    fn size_penalty_factor(&self) -> Option<f64> {
+        let vram = &self.config.vram;
+        if !vram.enabled || vram.size_penalty_factor <= 0.0 {
+            return None;
+        }
+        let info = get_vram_info()?;
+        (info.free_percent < vram.size_penalty_threshold).then(|| {
+            debug!("显存剩余 {:.1}%，按文件大小降低候选权重", info.free_percent);
+            vram.size_penalty_factor
+        })
+    }
+
+    /// 将壁纸标记为损坏，之后不再被选中
+    fn mark_broken(&mut self, path: &Path, reason: &str) {
+        warn!(
+            "壁纸无法读取或显示，已标记为损坏: {} ({})",
+            path.display(),
+            reason
+        );
+        if let Some(wall) = self.wallpapers.iter_mut().find(|w| w.path == path) {
+            wall.broken = true;
+        }
+        // 扫描索引不逐个检查文件，文件可能已在目录时间未更新的情况下被删除（如网络存储），
+        // 下次扫描时完整遍历
+        if !self.dry_run {
+            fs::remove_file(self.index_path()).ok();
+        }
+        if let Err(e) = self.save() {
+            warn!("无法写入缓存文件，损坏标记未保存: {}", e);
+        }
+    }
+
+    /// 符合标签过滤的壁纸（未设置过滤时为全部壁纸）
+    fn tagged_wallpapers(&self) -> Vec<Wallpaper> {
+        self.wallpapers
+            .iter()
+            .filter(|w| {
+                self.tag_filter.is_empty() || w.tags.iter().any(|t| self.tag_filter.contains(t))
+            })
+            .cloned()
+            .collect()
+    }
 
-        Some(selected)
+    /// 计算壁纸剩余的冷却时间（秒），不在冷却期内返回 None
+    fn cooldown_remaining(&self, wallpaper: &Wallpaper, now: u64) -> Option<u64> {
+        let cooldown = self.config.weight.min_cooldown_secs;
+        let last_played = wallpaper.last_played?;
+        let elapsed = now.saturating_sub(last_played);
+
+        if cooldown > 0 && elapsed < cooldown {
+            Some(cooldown - elapsed)
+        } else {
+            None
+        }
     }
 
     /// 设置壁纸并更新权重
+    ///
+    /// extension_engine 中映射的扩展名由对应引擎显示；未映射的 GIF 在静态壁纸引擎为 swww 时
+    /// 始终由 swww 显示，与当前模式无关
     pub fn set_wallpaper(&mut self, wallpaper: &Wallpaper) -> Result<(), String> {
-        self.engine.set_wallpaper(&wallpaper.path)?;
+        let routed = self.route(&wallpaper.path);
+        // 新引擎显示成功后才更新 routed_active，失败时仍记得需要停止的路由引擎
+        let previous = self
+            .routed_active
+            .clone()
+            .filter(|prev| Some(prev) != routed.as_ref());
+        let stop_previous = |engines: &HashMap<String, Box<dyn PaperEngine>>| {
+            if let Some(engine) = previous.as_ref().and_then(|prev| engines.get(prev)) {
+                engine.stop().ok();
+            }
+        };
+
+        match routed.as_ref().and_then(|r| self.routed_engines.get(r)) {
+            Some(engine) => {
+                debug!("{} 交给 {} 显示", wallpaper.path.display(), engine.name());
+                self.engine.stop()?;
+                stop_previous(&self.routed_engines);
+                // 路由引擎可能播放动画，不使用预缩放（会丢失动画帧）
+                engine.set_wallpaper(&wallpaper.path)?;
+                self.routed_active = routed;
+                self.shown = vec![wallpaper.path.clone()];
+            }
+            None => {
+                let display = self.display_path(&wallpaper.path);
+                self.engine.set_wallpaper(&display)?;
+                stop_previous(&self.routed_engines);
+                self.routed_active = None;
+                self.shown = vec![display];
+            }
+        }
+        Config::save_current_wallpapers(&[&wallpaper.path]);
 
         // 找到选中壁纸的索引
         let selected_idx = self
@@ -175,96 +992,1920 @@ impl WallManager {
             .ok_or("无法找到选中的壁纸")?;
 
         self.update_weights(selected_idx);
+        self.run_switch_hook(selected_idx);
+        palette::export(&self.config.palette, &wallpaper.path);
 
         Ok(())
     }
 
-    /// 切换到下一张壁纸（pick_next + set_wallpaper）
-    pub fn next(&mut self) -> Result<(), String> {
-        let wallpaper = self.pick_next().ok_or("没有可用的壁纸")?;
-        println!("切换到: {}", wallpaper.path.display());
-        self.set_wallpaper(&wallpaper)
-    }
-
-    /// 更新所有壁纸的权重（零和博弈）
-    fn update_weights(&mut self, selected_index: usize) {
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+    /// 切换成功后在后台执行 hooks.on_switch_command，失败只记录日志
+    ///
+    /// 占位符替换为环境变量引用再交给 sh -c，文件名中的引号和 $ 不会被 shell 解释
+    fn run_switch_hook(&self, idx: usize) {
+        let Some(template) = self
+            .config
+            .hooks
+            .on_switch_command
+            .as_deref()
+            .filter(|c| !c.trim().is_empty())
+        else {
+            return;
+        };
+        let Some(wall) = self.wallpapers.get(idx) else {
+            return;
+        };
 
-        // 使用零和博弈算法更新权重
-        self.weight_calc
-            .update_weights_zero_sum(&mut self.wallpapers, selected_index);
+        let name = wall
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut command = std::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(expand_hook_command(template))
+            .env("LIANWALL_PATH", &wall.path)
+            .env("LIANWALL_NAME", name)
+            .env("LIANWALL_WEIGHT", format!("{:.2}", wall.value));
 
-        // 更新 last_played 时间
-        if let Some(wall) = self.wallpapers.get_mut(selected_index) {
-            wall.last_played = Some(now);
+        // 子进程在调用方退出后继续运行（如 lianwall next），后台线程只负责回收并记录退出状态
+        match command.spawn() {
+            Ok(mut child) => {
+                thread::spawn(move || match child.wait() {
+                    Ok(status) if !status.success() => warn!("切换钩子命令异常退出: {}", status),
+                    Err(e) => warn!("等待切换钩子命令失败: {}", e),
+                    Ok(_) => {}
+                });
+            }
+            Err(e) => warn!("无法执行切换钩子命令: {}", e),
         }
+    }
 
-        self.save();
+    /// 等待最近一次设置的壁纸实际显示（最多 timeout），返回是否已确认显示
+    pub fn wait_until_shown(&self, timeout: Duration) -> bool {
+        let engine = self
+            .routed_active
+            .as_ref()
+            .and_then(|routed| self.routed_engines.get(routed))
+            .unwrap_or(&self.engine);
+        engine.wait_until_shown(&self.shown, timeout)
     }
 
-    /// 热重载：重新扫描目录并合并权重
-    pub fn reset(&mut self) {
-        let mode_str = match self.mode {
-            WallpaperMode::Video => "动态壁纸",
-            WallpaperMode::Image => "静态壁纸",
+    /// 按扩展名选择显示该文件的路由引擎类型，None 表示使用本模式的默认引擎
+    fn route(&self, path: &Path) -> Option<String> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        let routed = match self
+            .config
+            .extension_engines()
+            .into_iter()
+            .find(|(mapped, _)| *mapped == ext)
+        {
+            Some((_, engine)) => engine.to_string(),
+            None if self.gif_to_swww && ext == "gif" => "swww".to_string(),
+            None => return None,
         };
-        println!("重新扫描{}目录...", mode_str);
-        self.load_and_scan();
-        println!("发现 {} 个壁纸文件", self.wallpapers.len());
+        self.routed_engines.contains_key(&routed).then_some(routed)
     }
 
-    /// 获取状态信息
-    pub fn status(&self) -> String {
-        let stats = WallpaperSelector::get_stats(&self.wallpapers);
-        let mode_str = match self.mode {
-            WallpaperMode::Video => "动态壁纸 (Video)",
-            WallpaperMode::Image => "静态壁纸 (Image)",
+    /// 实际交给引擎显示的文件：启用预缩放时为缩放后的缓存图片，否则为原文件
+    fn display_path(&self, path: &Path) -> PathBuf {
+        let Some(screen) = self.screen_size.filter(|_| !is_gif(path)) else {
+            return path.to_path_buf();
         };
-        let interval = self.config.interval(self.mode);
-        format!(
-            "=== LianWall 状态 ===\n模式: {}\n引擎: {}\n切换间隔: {}秒\n\n{}\n\n--- 壁纸列表 ---",
-            mode_str,
-            self.engine.name(),
-            interval,
-            stats
-        )
+        let optimization = &self.config.image_optimization;
+        let cache_dir = Config::expand_path(&optimization.cache_dir);
+        match get_or_resize_image(path, &cache_dir, screen, optimization.min_free_mb) {
+            Ok(Some(resized)) => {
+                debug!("使用缩放后的图片: {}", resized.display());
+                resized
+            }
+            Ok(None) => path.to_path_buf(),
+            Err(e) => {
+                warn!("图片预缩放失败，使用原图: {}", e);
+                path.to_path_buf()
+            }
+        }
     }
 
-    /// 获取详细壁纸列表
-    pub fn list_wallpapers(&self) -> String {
-        let mut output = String::new();
-        let mut sorted = self.wallpapers.clone();
-        sorted.sort_by(|a, b| b.value.partial_cmp(&a.value).unwrap());
+    /// 当前（最近一次播放的）壁纸
+    pub fn current(&self) -> Option<&Path> {
+        self.last_path.as_deref()
+    }
 
-        for (i, w) in sorted.iter().enumerate() {
-            let filename = w
-                .path
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_else(|| "unknown".to_string());
-            output.push_str(&format!(
-                "{:2}. [{:6.2}] (跳过:{}) {}\n",
-                i + 1,
-                w.value,
-                w.skip_streak,
-                filename
-            ));
+    /// 显示指定壁纸并更新权重（路径需在壁纸库中）
+    pub fn show(&mut self, path: &Path) -> Result<(), String> {
+        let idx = self
+            .find_wallpaper(path)
+            .ok_or_else(|| format!("壁纸不在缓存中: {}", path.display()))?;
+        let wallpaper = self.wallpapers[idx].clone();
+        if let Err(e) = probe_file(&wallpaper.path) {
+            self.mark_broken(&wallpaper.path, &e);
+            return Err(format!("壁纸无法读取: {} ({})", path.display(), e));
+        }
+        self.set_wallpaper(&wallpaper)
+    }
+
+    /// 固定的壁纸被其他程序替换时重新显示它，返回是否重新显示
+    ///
+    /// 依次检查记录的当前壁纸和引擎状态（引擎无法查询时只检查记录）
+    pub fn reassert_pinned(&mut self, pin: &Path) -> Result<bool, String> {
+        let canonical = fs::canonicalize(pin).ok();
+        let is_pin = |path: &PathBuf| {
+            path == pin || (canonical.is_some() && fs::canonicalize(path).ok() == canonical)
+        };
+        let recorded = Config::load_current_wallpapers();
+        if !recorded.is_empty() && recorded.iter().all(is_pin) {
+            let engine = self
+                .route(pin)
+                .and_then(|routed| self.routed_engines.get(&routed))
+                .unwrap_or(&self.engine);
+            if engine.is_showing(&[self.display_path(pin)]) != Some(false) {
+                return Ok(false);
+            }
+        }
+
+        info!("固定的壁纸已被替换，重新显示: {}", pin.display());
+        self.show(pin).map(|_| true)
+    }
+
+    /// 按路径查找壁纸索引（同时比较规范化后的路径）
+    pub fn find_wallpaper(&self, path: &Path) -> Option<usize> {
+        let canonical = fs::canonicalize(path).ok();
+        self.wallpapers.iter().position(|w| {
+            w.path == path || (canonical.is_some() && fs::canonicalize(&w.path).ok() == canonical)
+        })
+    }
+
+    /// 手动调整壁纸权重（like / dislike），返回调整后的权重
+    pub fn adjust_weight(&mut self, path: &Path, delta: f64) -> Result<f64, String> {
+        let idx = self
+            .find_wallpaper(path)
+            .ok_or_else(|| format!("壁纸不在缓存中: {}", path.display()))?;
+
+        let wall = &mut self.wallpapers[idx];
+        wall.value = (wall.value + delta).max(1.0);
+        let value = wall.value;
+
+        self.save()
+            .map_err(|e| format!("无法写入缓存文件: {}", e))?;
+        Ok(value)
+    }
+
+    /// 导出权重缓存到指定文件
+    pub fn export_cache(&self, out: &Path) -> io::Result<usize> {
+        let content = self.serialize_cache().map_err(io::Error::other)?;
+        fs::write(out, content)?;
+        Ok(self.wallpapers.len() + self.dormant.len())
+    }
+
+    /// 序列化为带版本号的缓存文件内容
+    fn serialize_cache(&self) -> serde_json::Result<String> {
+        let cache = CacheFile {
+            version: CACHE_VERSION,
+            wallpapers: self.wallpapers.iter().chain(&self.dormant).collect(),
+            cursor: self.cursor.clone(),
+        };
+        serde_json::to_string_pretty(&cache)
+    }
+
+    /// 从外部缓存文件合并权重，返回（合并数量, 忽略数量）
+    ///
+    /// 按路径匹配，重叠的条目使用导入的 value / skip_streak / last_played / play_count；
+    /// 不在当前壁纸库中的条目被忽略
+    pub fn import_cache(&mut self, file: &Path) -> Result<(usize, usize), String> {
+        let content =
+            fs::read_to_string(file).map_err(|e| format!("无法读取 {}: {}", file.display(), e))?;
+        let imported = parse_cache(&content)
+            .map_err(|e| format!("无法解析 {}: {}", file.display(), e))?
+            .wallpapers;
+
+        let mut merged = 0;
+        for entry in &imported {
+            let target = self
+                .wallpapers
+                .iter_mut()
+                .chain(self.dormant.iter_mut())
+                .find(|w| w.path == entry.path);
+            if let Some(wall) = target {
+                wall.value = entry.value;
+                wall.skip_streak = entry.skip_streak;
+                wall.last_played = entry.last_played;
+                wall.play_count = entry.play_count;
+                merged += 1;
+            }
+        }
+
+        self.save()
+            .map_err(|e| format!("无法写入缓存文件: {}", e))?;
+        Ok((merged, imported.len() - merged))
+    }
+
+    /// 查找内容相同的壁纸，每组第一个为保留的壁纸
+    ///
+    /// 按内容标识（文件大小与前 1MB 的哈希）分组，只比较文件开头，大小相同、开头相同但后面不同的
+    /// 文件也会被分为一组（删除文件前用 `same_content` 逐字节确认）；同一视频的不同格式不会被识别。
+    /// 保留未被标记的、播放次数最多的一份，相同时取路径靠前的
+    pub fn find_duplicates(&mut self) -> Vec<Vec<PathBuf>> {
+        let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, wall) in self.wallpapers.iter_mut().enumerate() {
+            // 旧版本缓存中的条目可能没有内容标识
+            if wall.content_hash.is_none() {
+                wall.content_hash = content_hash_with_timeout(&wall.path);
+            }
+            if let Some(hash) = &wall.content_hash {
+                groups.entry(hash.clone()).or_default().push(i);
+            }
+        }
+
+        let mut duplicates: Vec<Vec<PathBuf>> = groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort_by(|&a, &b| {
+                    let (a, b) = (&self.wallpapers[a], &self.wallpapers[b]);
+                    (a.duplicate_of.is_some(), Reverse(a.play_count), &a.path).cmp(&(
+                        b.duplicate_of.is_some(),
+                        Reverse(b.play_count),
+                        &b.path,
+                    ))
+                });
+                group
+                    .into_iter()
+                    .map(|i| self.wallpapers[i].path.clone())
+                    .collect()
+            })
+            .collect();
+        duplicates.sort();
+        duplicates
+    }
+
+    /// 将每组中除第一个以外的壁纸标记为副本，返回新标记的数量
+    pub fn mark_duplicates(&mut self, groups: &[Vec<PathBuf>]) -> io::Result<usize> {
+        let mut marked = 0;
+        for group in groups {
+            let Some((kept, copies)) = group.split_first() else {
+                continue;
+            };
+            for wall in self
+                .wallpapers
+                .iter_mut()
+                .filter(|w| copies.contains(&w.path))
+            {
+                if wall.duplicate_of.as_ref() != Some(kept) {
+                    wall.duplicate_of = Some(kept.clone());
+                    marked += 1;
+                }
+            }
+        }
+        self.save()?;
+        Ok(marked)
+    }
+
+    /// 清除所有副本标记，返回清除的数量
+    pub fn clear_duplicates(&mut self) -> io::Result<usize> {
+        let mut cleared = 0;
+        for wall in self.wallpapers.iter_mut().chain(self.dormant.iter_mut()) {
+            if wall.duplicate_of.take().is_some() {
+                cleared += 1;
+            }
+        }
+        self.save()?;
+        Ok(cleared)
+    }
+
+    /// 切换到下一张壁纸（pick_next + set_wallpaper）
+    ///
+    /// 引擎无法显示选中的文件时检查文件本身：文件损坏则标记后换一张，最多尝试 MAX_SWITCH_ATTEMPTS 次；
+    /// 文件完好时是引擎的问题（未安装、守护进程未就绪等），直接返回错误，不标记文件
+    pub fn next(&mut self) -> Result<(), String> {
+        if self.config.per_monitor(self.mode) {
+            let outputs = monitor::list_outputs();
+            if outputs.len() > 1 {
+                return self.next_per_monitor(&outputs);
+            }
+        }
+
+        let mut last_error = None;
+        for _ in 0..MAX_SWITCH_ATTEMPTS {
+            let Some(wallpaper) = self.pick_next() else {
+                break;
+            };
+            if self.dry_run {
+                println!(
+                    "🔍 [dry-run] 将切换到: {} (权重 {:.2})",
+                    wallpaper.path.display(),
+                    wallpaper.value
+                );
+                return Ok(());
+            }
+
+            info!("切换到: {}", wallpaper.path.display());
+            match self.set_wallpaper(&wallpaper) {
+                Ok(()) => return Ok(()),
+                Err(e) => match probe_content(&wallpaper.path) {
+                    Err(reason) => {
+                        self.mark_broken(&wallpaper.path, &reason);
+                        last_error = Some(e);
+                    }
+                    Ok(()) => return Err(e),
+                },
+            }
+        }
+        Err(last_error.unwrap_or_else(|| self.no_wallpaper_error()))
+    }
+
+    /// 没有可选壁纸时的错误信息
+    fn no_wallpaper_error(&self) -> String {
+        if self.tag_filter.is_empty() || self.wallpapers.is_empty() {
+            "没有可用的壁纸".to_string()
+        } else {
+            format!("没有带标签 {} 的壁纸", self.tag_filter.join(", "))
+        }
+    }
+
+    /// 为每个显示器分别选择并设置壁纸
+    fn next_per_monitor(&mut self, outputs: &[String]) -> Result<(), String> {
+        let mut assignments: Vec<(String, PathBuf)> = Vec::new();
+
+        for output in outputs {
+            let taken: Vec<PathBuf> = assignments.iter().map(|(_, p)| p.clone()).collect();
+            let wallpaper = self
+                .pick_next_excluding(&taken)
+                .ok_or_else(|| self.no_wallpaper_error())?;
+            if self.dry_run {
+                println!(
+                    "🔍 [dry-run] [{}] 将切换到: {} (权重 {:.2})",
+                    output,
+                    wallpaper.path.display(),
+                    wallpaper.value
+                );
+            } else {
+                info!("切换到: [{}] {}", output, wallpaper.path.display());
+            }
+            assignments.push((output.clone(), wallpaper.path));
+        }
+
+        if self.dry_run {
+            return Ok(());
+        }
+
+        let display: Vec<(String, PathBuf)> = assignments
+            .iter()
+            .map(|(output, path)| (output.clone(), self.display_path(path)))
+            .collect();
+        self.engine.set_wallpapers(&display)?;
+        self.shown = display.into_iter().map(|(_, path)| path).collect();
+        let paths: Vec<&PathBuf> = assignments.iter().map(|(_, p)| p).collect();
+        Config::save_current_wallpapers(&paths);
+
+        let selected: Vec<usize> = assignments
+            .iter()
+            .filter_map(|(_, path)| self.wallpapers.iter().position(|w| &w.path == path))
+            .collect();
+        self.update_weights_many(&selected);
+        for &idx in &selected {
+            self.run_switch_hook(idx);
+        }
+        // 配色取第一个输出的壁纸
+        if let Some((_, path)) = assignments.first() {
+            palette::export(&self.config.palette, path);
+        }
+
+        Ok(())
+    }
+
+    /// 只为指定输出切换到下一张壁纸，其他输出保持当前壁纸
+    ///
+    /// 输出名称必须是已连接的显示器；不做扩展名分流（与 per_monitor 相同）
+    pub fn next_on_output(&mut self, output: &str) -> Result<(), String> {
+        let outputs = monitor::list_outputs();
+        if outputs.is_empty() {
+            return Err(format!(
+                "无法获取显示器列表，不能确认输出 {} 是否存在",
+                output
+            ));
+        }
+        if !outputs.iter().any(|o| o == output) {
+            return Err(format!(
+                "未知的显示器: {}（已连接: {}）",
+                output,
+                outputs.join(", ")
+            ));
+        }
+
+        // 上次切换记录的壁纸：一行表示所有输出相同，多行按输出顺序对应
+        let current = Config::load_current_wallpapers();
+        let current_on = |i: usize| match current.len() {
+            1 => current.first(),
+            n if n == outputs.len() => current.get(i),
+            _ => None,
+        };
+        let others: Vec<(String, PathBuf)> = outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| *o != output)
+            .filter_map(|(i, o)| Some((o.clone(), current_on(i)?.clone())))
+            .collect();
+
+        let taken: Vec<PathBuf> = others.iter().map(|(_, p)| p.clone()).collect();
+        let wallpaper = self
+            .pick_next_excluding(&taken)
+            .ok_or_else(|| self.no_wallpaper_error())?;
+        if self.dry_run {
+            println!(
+                "🔍 [dry-run] [{}] 将切换到: {} (权重 {:.2})",
+                output,
+                wallpaper.path.display(),
+                wallpaper.value
+            );
+            return Ok(());
+        }
+        info!("切换到: [{}] {}", output, wallpaper.path.display());
+
+        let display = self.display_path(&wallpaper.path);
+        let others_display: Vec<(String, PathBuf)> = others
+            .iter()
+            .map(|(o, p)| (o.clone(), self.display_path(p)))
+            .collect();
+        self.engine
+            .set_wallpaper_only_on(output, &display, &others_display)?;
+        self.shown = vec![display];
+
+        // 按输出顺序记录，其他输出的壁纸未知时只记录本次的壁纸
+        let saved: Vec<&PathBuf> = if others.len() + 1 == outputs.len() {
+            outputs
+                .iter()
+                .filter_map(|o| {
+                    if o == output {
+                        Some(&wallpaper.path)
+                    } else {
+                        others.iter().find(|(other, _)| other == o).map(|(_, p)| p)
+                    }
+                })
+                .collect()
+        } else {
+            vec![&wallpaper.path]
+        };
+        Config::save_current_wallpapers(&saved);
+
+        if let Some(idx) = self
+            .wallpapers
+            .iter()
+            .position(|w| w.path == wallpaper.path)
+        {
+            self.update_weights(idx);
+            self.run_switch_hook(idx);
+        }
+        palette::export(&self.config.palette, &wallpaper.path);
+        Ok(())
+    }
+
+    /// 更新所有壁纸的权重（零和博弈）
+    fn update_weights(&mut self, selected_index: usize) {
+        self.update_weights_many(&[selected_index]);
+    }
+
+    /// 同时选中多张壁纸（多显示器）时更新权重：只做一轮零和博弈，只写入一次缓存
+    fn update_weights_many(&mut self, selected: &[usize]) {
+        let now = unix_now();
+
+        // 使用零和博弈算法更新权重
+        self.weight_calc
+            .update_weights_zero_sum(&mut self.wallpapers, selected);
+
+        // 更新 last_played 时间
+        for &idx in selected {
+            let Some(wall) = self.wallpapers.get_mut(idx) else {
+                continue;
+            };
+            wall.last_played = Some(now);
+            wall.play_count = wall.play_count.saturating_add(1);
+            // 扫描时不读取文件内容，在首次播放后记录内容标识，之后被移动时可以继承权重
+            if wall.content_hash.is_none() {
+                wall.content_hash = content_hash_with_timeout(&wall.path);
+            }
+            self.last_path = Some(wall.path.clone());
+            if self.config.weight.selection_strategy == SelectionStrategy::Sequential {
+                self.cursor = Some(wall.path.clone());
+            }
+
+            let limit = self.config.weight.session_no_repeat;
+            if limit > 0 {
+                self.recent.push_back(wall.path.clone());
+                while self.recent.len() > limit {
+                    self.recent.pop_front();
+                }
+            }
+        }
+
+        if let Err(e) = self.save() {
+            warn!("无法写入缓存文件，本次权重变化未保存: {}", e);
+        }
+    }
+
+    /// 时间段目录变化时切换扫描目录并重新加载权重
+    ///
+    /// 返回 true 表示目录发生了变化
+    pub fn refresh_schedule(&mut self) -> bool {
+        let dir = self.config.wallpaper_dir(self.mode);
+        if dir == self.scan_dir {
+            return false;
+        }
+
+        info!(
+            "时间段变化，壁纸目录切换: {} → {}",
+            self.scan_dir.display(),
+            dir.display()
+        );
+        self.load_and_scan(false);
+        info!("发现 {} 个壁纸文件", self.wallpapers.len());
+        true
+    }
+
+    /// 重新读取生效的标签过滤（`lianwall tag` 在运行时修改后由守护进程调用）
+    pub fn refresh_tags(&mut self) {
+        let tags = self.config.active_tags(self.mode);
+        if tags != self.tag_filter {
+            if tags.is_empty() {
+                info!("标签过滤已清除");
+            } else {
+                info!("标签过滤: {}", tags.join(", "));
+            }
+            self.tag_filter = tags;
+        }
+    }
+
+    /// 守护进程退出前再保存一次权重缓存（演练模式下不写入）
+    pub fn flush(&self) {
+        if let Err(e) = self.save() {
+            warn!("无法写入缓存文件，退出前的权重未保存: {}", e);
+        }
+    }
+
+    /// 热重载：重新扫描目录并合并权重
+    pub fn reset(&mut self) {
+        let mode_str = match self.mode {
+            WallpaperMode::Video => "动态壁纸",
+            WallpaperMode::Image => "静态壁纸",
+        };
+        info!("重新扫描{}目录...", mode_str);
+        self.load_and_scan(true);
+        info!("发现 {} 个壁纸文件", self.wallpapers.len());
+
+        // 损坏可能是暂时的（如文件还在下载），重新扫描时清除标记让其重新检查
+        let mut cleared = 0;
+        for wall in self.wallpapers.iter_mut().chain(self.dormant.iter_mut()) {
+            if wall.broken {
+                wall.broken = false;
+                cleared += 1;
+            }
+        }
+        if cleared > 0 {
+            info!("已清除 {} 个壁纸的损坏标记", cleared);
+            if let Err(e) = self.save() {
+                warn!("无法写入缓存文件，损坏标记未清除: {}", e);
+            }
+        }
+    }
+
+    /// 彻底重置：删除缓存文件，按首次运行重新初始化所有壁纸的权重
+    ///
+    /// 返回被丢弃的权重记录数（包括其他时间段目录中的记录）
+    pub fn hard_reset(&mut self) -> io::Result<usize> {
+        let discarded = self.wallpapers.len() + self.dormant.len();
+        let cache_path = self.config.cache_path(self.mode);
+        match fs::remove_file(&cache_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("{}: {}", cache_path.display(), e),
+                ));
+            }
+            _ => {}
+        }
+
+        self.load_and_scan(true);
+        Ok(discarded)
+    }
+
+    /// 升级旧版本的缓存文件
+    ///
+    /// 旧缓存中缺失的字段在加载时已由 serde 默认值补全，这里将其以当前格式重新写入一次；
+    /// 由 load_and_scan（包括 reset）在检测到旧版本时调用
+    pub fn migrate_cache(&mut self) {
+        if self.cache_version >= CACHE_VERSION {
+            return;
+        }
+
+        info!("升级缓存格式: v{} → v{}", self.cache_version, CACHE_VERSION);
+        match self.save() {
+            Ok(()) => self.cache_version = CACHE_VERSION,
+            Err(e) => warn!("无法写入缓存文件，缓存格式未升级: {}", e),
+        }
+    }
+
+    /// 获取状态信息
+    pub fn status(&self) -> String {
+        let stats = WallpaperSelector::get_stats(&self.wallpapers);
+        let mode_str = match self.mode {
+            WallpaperMode::Video => "动态壁纸 (Video)",
+            WallpaperMode::Image => "静态壁纸 (Image)",
+        };
+        let interval = self.config.interval(self.mode);
+        let rotation = if let Some(pinned) = Config::load_pinned() {
+            format!("已固定 {}", pinned.display())
+        } else if Config::is_paused() {
+            "已暂停".to_string()
+        } else {
+            "运行中".to_string()
+        };
+        let tags = if self.tag_filter.is_empty() {
+            "无".to_string()
+        } else {
+            self.tag_filter.join(", ")
+        };
+        format!(
+            "=== LianWall 状态 ===\n模式: {}\n引擎: {}\n切换间隔: {}秒\n自动轮换: {}\n标签过滤: {}\n\n{}\n\n--- 壁纸列表 ---",
+            mode_str,
+            self.engine.name(),
+            interval,
+            rotation,
+            tags,
+            stats
+        )
+    }
+
+    /// 获取 JSON 格式的状态信息
+    pub fn status_json(&self) -> Result<String, String> {
+        let report = StatusReport {
+            mode: match self.mode {
+                WallpaperMode::Video => "video",
+                WallpaperMode::Image => "image",
+            },
+            engine: self.engine.name(),
+            interval: self.config.interval(self.mode),
+            paused: Config::is_paused(),
+            pinned: Config::load_pinned(),
+            current: self.last_path.clone(),
+            current_percentile: self.current().and_then(|p| self.percentile_of(p)),
+            stats: WallpaperSelector::get_stats(&self.wallpapers),
+            wallpapers: &self.tagged_wallpapers(),
+        };
+        serde_json::to_string_pretty(&report).map_err(|e| {
+            error!("序列化状态信息失败: {}", e);
+            format!("序列化状态信息失败: {}", e)
+        })
+    }
+
+    /// 计算指定壁纸的权重在壁纸库中的百分位（0.0 最低，1.0 最高）
+    ///
+    /// 壁纸不在壁纸库中时返回 None
+    pub fn percentile_of(&self, path: &Path) -> Option<f64> {
+        let idx = self.find_wallpaper(path)?;
+        Some(rank_percentile(
+            &self.wallpapers,
+            self.wallpapers[idx].value,
+        ))
+    }
+
+    /// 获取详细壁纸列表（只包含符合标签过滤的壁纸）
+    ///
+    /// JSON 序列化失败时返回错误
+    pub fn list_wallpapers(&self, format: ListFormat, sort: SortKey) -> Result<String, String> {
+        let mut sorted = self.tagged_wallpapers();
+        sorted.sort_by(|a, b| sort.compare(a, b));
+
+        match format {
+            ListFormat::Table => Ok(self.list_table(&sorted)),
+            ListFormat::Csv => Ok(list_csv(&sorted)),
+            ListFormat::Json => serde_json::to_string_pretty(&sorted)
+                .map(|json| json + "\n")
+                .map_err(|e| {
+                    error!("序列化壁纸列表失败: {}", e);
+                    format!("序列化壁纸列表失败: {}", e)
+                }),
+        }
+    }
+
+    /// 以表格形式列出壁纸
+    fn list_table(&self, sorted: &[Wallpaper]) -> String {
+        let mut output = String::new();
+        let now = unix_now();
+
+        for (i, w) in sorted.iter().enumerate() {
+            let filename = w
+                .path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            let cooldown = self
+                .cooldown_remaining(w, now)
+                .map(|secs| format!(" [冷却中 {}秒]", secs))
+                .unwrap_or_default();
+            let broken = if w.broken {
+                " [损坏]"
+            } else if w.duplicate_of.is_some() {
+                " [重复]"
+            } else {
+                ""
+            };
+            let tags = if w.tags.is_empty() {
+                String::new()
+            } else {
+                format!(" [标签: {}]", w.tags.join(", "))
+            };
+            output.push_str(&format!(
+                "{:2}. [{:6.2}] (跳过:{}) {}{}{}{}\n",
+                i + 1,
+                w.value,
+                w.skip_streak,
+                filename,
+                broken,
+                cooldown,
+                tags
+            ));
+        }
+        output
+    }
+
+    /// 获取按播放次数排序的统计表
+    pub fn play_stats(&self) -> String {
+        let mut sorted = self.wallpapers.clone();
+        sorted.sort_by(|a, b| {
+            b.play_count
+                .cmp(&a.play_count)
+                .then(b.value.total_cmp(&a.value))
+        });
+
+        let mut output = String::from(" #  播放次数    权重  跳过  文件\n");
+        for (i, w) in sorted.iter().enumerate() {
+            let filename = w
+                .path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            output.push_str(&format!(
+                "{:2}. {:8} {:7.2} {:5}  {}\n",
+                i + 1,
+                w.play_count,
+                w.value,
+                w.skip_streak,
+                filename
+            ));
         }
         output
     }
 
     /// 保存壁纸数据到缓存文件
-    fn save(&self) {
+    ///
+    /// 写入失败（只读目录、磁盘已满等）时返回错误，由调用方决定如何处理；
+    /// 演练模式下不写入
+    fn save(&self) -> io::Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+        if self.cache_unreadable {
+            return Err(io::Error::other("加载时无法读取缓存文件，不覆盖已有的权重"));
+        }
         let cache_path = self.config.cache_path(self.mode);
 
         if let Some(parent) = cache_path.parent() {
-            fs::create_dir_all(parent).ok();
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = self.serialize_cache().map_err(|e| {
+            error!("序列化壁纸数据失败: {}", e);
+            io::Error::other(e)
+        })?;
+        write_atomic(&cache_path, content.as_bytes())
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", cache_path.display(), e)))
+    }
+}
+
+/// 以 CSV 形式列出壁纸，带表头
+fn list_csv(wallpapers: &[Wallpaper]) -> String {
+    let mut output = String::from("path,value,skip_streak,last_played,play_count,tags\n");
+    for w in wallpapers {
+        output.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            csv_field(&w.path.to_string_lossy()),
+            w.value,
+            w.skip_streak,
+            w.last_played.map(|t| t.to_string()).unwrap_or_default(),
+            w.play_count,
+            csv_field(&w.tags.join(";"))
+        ));
+    }
+    output
+}
+
+/// CSV 字段转义：包含逗号、引号或换行时加引号，引号加倍
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// sidecar 标签文件
+#[derive(Deserialize)]
+struct Sidecar {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// 读取壁纸的 sidecar 标签文件，没有 sidecar 时返回空列表
+///
+/// 优先读取 `<文件名>.toml` 中的 `tags = [...]`，其次读取 `<文件名>.tags`
+/// （以逗号或换行分隔，`#` 开头的行为注释）；标签统一转为小写
+fn read_sidecar_tags(path: &Path) -> Vec<String> {
+    let toml_path = path_with_suffix(path, ".toml");
+    let tags_path = path_with_suffix(path, ".tags");
+
+    let raw: Vec<String> = if let Ok(content) = fs::read_to_string(&toml_path) {
+        match toml::from_str::<Sidecar>(&content) {
+            Ok(sidecar) => sidecar.tags,
+            Err(e) => {
+                warn!("无法解析标签文件 {}: {}", toml_path.display(), e);
+                Vec::new()
+            }
+        }
+    } else if let Ok(content) = fs::read_to_string(&tags_path) {
+        content
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split(','))
+            .map(str::to_string)
+            .collect()
+    } else {
+        return Vec::new();
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw.iter().map(|t| t.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// sidecar 标签文件的修改时间，与 `read_sidecar_tags` 一样 .toml 优先
+fn sidecar_modified(path: &Path) -> Option<SystemTime> {
+    [".toml", ".tags"].iter().find_map(|suffix| {
+        fs::metadata(path_with_suffix(path, suffix))
+            .and_then(|m| m.modified())
+            .ok()
+    })
+}
+
+/// 模式对应的引擎配置项
+fn engine_key(mode: WallpaperMode) -> &'static str {
+    match mode {
+        WallpaperMode::Video => "video_engine.type",
+        WallpaperMode::Image => "image_engine.type",
+    }
+}
+
+/// 计算内容标识时读取的字节数
+const CONTENT_HASH_BYTES: u64 = 1024 * 1024;
+
+/// 计算文件的内容标识：前 1MB 内容的 FNV-1a 哈希加文件大小
+///
+/// 使用固定的哈希算法，保证不同版本、不同机器间结果一致；读取失败时返回 None
+fn content_hash(path: &Path) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut head = Vec::new();
+    file.take(CONTENT_HASH_BYTES).read_to_end(&mut head).ok()?;
+    Some(format!("{:016x}-{}", fnv1a(&head), len))
+}
+
+/// 64 位 FNV-1a 哈希
+///
+/// 用于写入磁盘的标识（内容标识、缓存文件名），不能使用标准库的 DefaultHasher：
+/// 它的算法不保证在不同 Rust 版本间保持一致
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// 逐字节比较两个文件的内容是否相同
+pub fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    const CHUNK_BYTES: u64 = 64 * 1024;
+
+    let (mut a, mut b) = (fs::File::open(a)?, fs::File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut chunk_a, mut chunk_b) = (Vec::new(), Vec::new());
+    loop {
+        chunk_a.clear();
+        chunk_b.clear();
+        (&mut a).take(CHUNK_BYTES).read_to_end(&mut chunk_a)?;
+        (&mut b).take(CHUNK_BYTES).read_to_end(&mut chunk_b)?;
+        if chunk_a != chunk_b {
+            return Ok(false);
+        }
+        if chunk_a.is_empty() {
+            return Ok(true);
+        }
+    }
+}
+
+/// 检查壁纸文件是否可读且非空
+///
+/// 只读取文件开头，能发现空文件、权限错误等明显的损坏，不检查编码内容
+fn probe_file(path: &Path) -> Result<(), String> {
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut buf = [0u8; 16];
+    match file.read(&mut buf) {
+        Ok(0) => Err("文件为空".to_string()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 引擎显示失败后检查文件内容是否损坏
+///
+/// 除 `probe_file` 的检查外，image 支持的格式读取图片头，其他格式在安装了 ffprobe 时用它探测；
+/// 无法探测时视为完好，避免把引擎的错误归咎于文件
+fn probe_content(path: &Path) -> Result<(), String> {
+    probe_file(path)?;
+    if image::ImageFormat::from_path(path).is_ok() {
+        return image::image_dimensions(path)
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+    }
+
+    let output = std::process::Command::new("ffprobe")
+        .args(["-v", "error"])
+        .arg(path)
+        .output();
+    match output {
+        Ok(output) if !output.status.success() => Err(format!(
+            "ffprobe 无法读取: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 按文件大小降低候选壁纸的权重：每 GB 扣除 factor，最低保留 1.0
+///
+/// 只修改选择用的副本，不影响缓存中的权重；无法读取大小的文件不做调整
+fn apply_size_penalty(pool: &mut [Wallpaper], factor: f64, size_of: impl Fn(&Path) -> Option<u64>) {
+    const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    for wall in pool.iter_mut() {
+        if let Some(size) = size_of(&wall.path) {
+            wall.value = (wall.value - factor * size as f64 / BYTES_PER_GB).max(1.0);
+        }
+    }
+}
+
+/// 用 ffprobe 读取媒体时长（秒），未安装 ffprobe 或读取失败时返回 None
+fn probe_duration(path: &Path) -> Option<f64> {
+    let output = std::process::Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(path)
+        .output()
+        .map_err(|e| debug!("无法执行 ffprobe: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        debug!(
+            "ffprobe 无法读取 {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|d| d.is_finite() && *d >= 0.0)
+}
+
+/// 将钩子命令中的 {path} / {name} / {weight} 替换为对应的环境变量引用
+fn expand_hook_command(template: &str) -> String {
+    template
+        .replace("{path}", "${LIANWALL_PATH}")
+        .replace("{name}", "${LIANWALL_NAME}")
+        .replace("{weight}", "${LIANWALL_WEIGHT}")
+}
+
+/// 文件加入壁纸库的时间
+///
+/// 复制文件时修改时间可能被保留（cp -p、rsync -t）也可能被重置，不能可靠反映加入时间，
+/// 因此优先使用创建时间（birth time）；平台或文件系统不支持时（如部分网络存储、
+/// 旧内核上的 statx 不可用）回退到修改时间，都无法获取时视为最旧
+fn added_time(metadata: &fs::Metadata) -> SystemTime {
+    metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// 在独立线程中执行文件操作，超时返回 None
+///
+/// 挂起的网络文件系统不会阻塞调用方；超时的线程会被放弃，待文件系统恢复后自行结束
+fn with_timeout<T: Send + 'static>(
+    path: &Path,
+    timeout: Duration,
+    op: fn(&Path) -> T,
+) -> Option<T> {
+    let (tx, rx) = mpsc::channel();
+    let owned = path.to_path_buf();
+    thread::spawn(move || {
+        tx.send(op(&owned)).ok();
+    });
+    rx.recv_timeout(timeout).ok()
+}
+
+/// 读取文件元数据，失败或超时返回 None 并打印警告
+fn metadata_with_timeout(path: &Path, timeout: Duration) -> Option<fs::Metadata> {
+    match with_timeout(path, timeout, |path| fs::metadata(path)) {
+        Some(Ok(metadata)) => Some(metadata),
+        Some(Err(e)) => {
+            warn!("跳过无法读取的文件 {}: {}", path.display(), e);
+            None
+        }
+        None => {
+            warn!("读取文件超时，已跳过: {}", path.display());
+            None
+        }
+    }
+}
+
+/// 确认文件已不存在；读取失败或超时时无法确认，返回 false
+fn is_missing(path: &Path) -> bool {
+    matches!(
+        with_timeout(path, METADATA_TIMEOUT, |path| path.try_exists()),
+        Some(Ok(false))
+    )
+}
+
+/// 计算内容标识，读取超时（如网络存储离线）时返回 None
+fn content_hash_with_timeout(path: &Path) -> Option<String> {
+    with_timeout(path, CONTENT_HASH_TIMEOUT, content_hash).flatten()
+}
+
+/// 计算权重 value 的百分位：权重严格低于它的壁纸所占比例
+///
+/// 最高权重为 1.0，最低权重为 0.0，只有一张壁纸时为 1.0
+fn rank_percentile(wallpapers: &[Wallpaper], value: f64) -> f64 {
+    let stats = WallpaperSelector::get_stats(wallpapers);
+    if stats.count <= 1 || value >= stats.max_value {
+        return 1.0;
+    }
+    let below = wallpapers.iter().filter(|w| w.value < value).count();
+    below as f64 / (stats.count - 1) as f64
+}
+
+/// 扫描的文件扩展名：本模式引擎支持的扩展名加上 extension_engine 中的扩展名
+fn scan_extensions(config: &Config, mode: WallpaperMode) -> Result<Vec<String>, String> {
+    let mut extensions: Vec<String> = supported_extensions(config.engine_type(mode))?
+        .iter()
+        .map(|ext| ext.to_string())
+        .collect();
+    for (ext, _) in config.extension_engines() {
+        if !extensions.contains(&ext) {
+            extensions.push(ext);
+        }
+    }
+    Ok(extensions)
+}
+
+/// 遍历壁纸目录，返回遍历到的各级目录及其修改时间（读取失败为 None）和壁纸文件
+fn walk_wallpapers(
+    scan_dir: &Path,
+    extensions: &[String],
+    exclude: &[glob::Pattern],
+    follow_links: bool,
+    skip_hidden: bool,
+) -> (Vec<(PathBuf, Option<SystemTime>)>, Vec<PathBuf>) {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(scan_dir)
+        .follow_links(follow_links)
+        .into_iter()
+        // 扫描根目录本身即使是隐藏目录也照常扫描
+        .filter_entry(|e| !(skip_hidden && e.depth() > 0 && is_hidden(e.file_name())))
+        .filter_map(|e| e.map_err(|e| warn!("跳过无法访问的条目: {}", e)).ok())
+    {
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            let mtime = entry.metadata().ok().and_then(|m| m.modified().ok());
+            dirs.push((path.to_path_buf(), mtime));
+        } else if entry.file_type().is_file()
+            && !is_excluded(path, exclude)
+            && path
+                .extension()
+                .is_some_and(|ext| extensions.contains(&ext.to_string_lossy().to_lowercase()))
+        {
+            files.push(path.to_path_buf());
+        }
+    }
+    (dirs, files)
+}
+
+/// 文件名或目录名是否以 . 开头
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+/// 文件是否匹配任一排除规则（完整路径或文件名）
+fn is_excluded(path: &Path, exclude: &[glob::Pattern]) -> bool {
+    exclude.iter().any(|p| {
+        p.matches_path(path)
+            || path
+                .file_name()
+                .is_some_and(|name| p.matches(&name.to_string_lossy()))
+    })
+}
+
+/// 判断文件是否为 GIF
+fn is_gif(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+}
+
+/// 在文件名后追加后缀，例如 video.json → video.json.bak
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 原子写入文件：先写入同目录下的临时文件，再重命名覆盖目标文件
+///
+/// 进程在写入过程中被杀死时，目标文件保持旧内容而不会被截断
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp_path = path_with_suffix(path, ".tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path).inspect_err(|_| {
+        fs::remove_file(&tmp_path).ok();
+    })
+}
+
+/// 当前 Unix 时间戳（秒）
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// 在 dir/videos 中创建 names 列出的文件（内容为文件名），返回该目录和指向它的配置
+    fn test_config(dir: &Path, names: &[&str]) -> (PathBuf, Config) {
+        let videos = dir.join("videos");
+        fs::create_dir_all(&videos).unwrap();
+        for name in names {
+            fs::write(videos.join(name), name).unwrap();
+        }
+
+        let mut config = Config::default();
+        config.paths.video_dir = videos.to_string_lossy().into_owned();
+        config.paths.video_cache = dir.join("video.json").to_string_lossy().into_owned();
+        (videos, config)
+    }
+
+    /// 用 test_config 的配置创建视频模式的 WallManager
+    fn test_manager(dir: &Path, names: &[&str]) -> WallManager {
+        let (_, config) = test_config(dir, names);
+        WallManager::try_new(config, WallpaperMode::Video, false).unwrap()
+    }
+
+    #[test]
+    fn test_rank_percentile() {
+        let wallpapers: Vec<Wallpaper> = [50.0, 100.0, 150.0, 200.0, 250.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| Wallpaper {
+                path: PathBuf::from(format!("/{}.mp4", i)),
+                value,
+                ..Default::default()
+            })
+            .collect();
+
+        assert_eq!(rank_percentile(&wallpapers, 250.0), 1.0);
+        assert_eq!(rank_percentile(&wallpapers, 50.0), 0.0);
+        assert_eq!(rank_percentile(&wallpapers, 150.0), 0.5);
+        assert_eq!(rank_percentile(&wallpapers[..1], 50.0), 1.0);
+    }
+
+    #[test]
+    fn test_csv_field_quotes_special_chars() {
+        assert_eq!(csv_field("/v/a.mp4"), "/v/a.mp4");
+        assert_eq!(csv_field("/v/a,b.mp4"), "\"/v/a,b.mp4\"");
+        assert_eq!(csv_field("/v/say \"hi\".mp4"), "\"/v/say \"\"hi\"\".mp4\"");
+    }
+
+    #[test]
+    fn test_parse_cache_versions() {
+        let legacy =
+            r#"[{"path": "/a.mp4", "value": 120.0, "skip_streak": 2, "last_played": null}]"#;
+        let CacheFile {
+            version,
+            wallpapers,
+            cursor,
+        } = parse_cache(legacy).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(cursor, None);
+        assert_eq!(wallpapers[0].value, 120.0);
+        assert_eq!(wallpapers[0].play_count, 0);
+
+        let versioned = serde_json::to_string(&CacheFile {
+            version: CACHE_VERSION,
+            wallpapers: vec![&wallpapers[0]],
+            cursor: Some(wallpapers[0].path.clone()),
+        })
+        .unwrap();
+        let parsed = parse_cache(&versioned).unwrap();
+        assert_eq!(parsed.version, CACHE_VERSION);
+        assert_eq!(parsed.wallpapers[0].path, wallpapers[0].path);
+        assert_eq!(parsed.cursor, Some(wallpapers[0].path.clone()));
+
+        assert!(parse_cache("[{\"path\":").is_err());
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("video.json");
+
+        fs::write(&path, "old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!path_with_suffix(&path, ".tmp").exists());
+    }
+
+    #[test]
+    fn test_read_sidecar_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let clip = dir.path().join("clip.mp4");
+        let image = dir.path().join("forest.png");
+        let plain = dir.path().join("plain.mp4");
+
+        fs::write(
+            path_with_suffix(&clip, ".toml"),
+            "tags = [\"Anime\", \"minimal\"]",
+        )
+        .unwrap();
+        fs::write(
+            path_with_suffix(&image, ".tags"),
+            "# 注释\nnature, forest\nNature\n",
+        )
+        .unwrap();
+
+        assert_eq!(read_sidecar_tags(&clip), vec!["anime", "minimal"]);
+        assert_eq!(read_sidecar_tags(&image), vec!["nature", "forest"]);
+        assert!(read_sidecar_tags(&plain).is_empty());
+    }
+
+    #[test]
+    fn test_hook_command_placeholders_are_not_shell_interpreted() {
+        let command = expand_hook_command(r#"printf '%s|%s' "{name}" {weight}"#);
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("LIANWALL_NAME", "it's $HOME \"quoted\".mp4")
+            .env("LIANWALL_WEIGHT", "101.50")
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "it's $HOME \"quoted\".mp4|101.50"
+        );
+    }
+
+    #[test]
+    fn test_extension_engine_routing() {
+        let dir = tempfile::tempdir().unwrap();
+        let images = dir.path().join("images");
+        fs::create_dir_all(&images).unwrap();
+        fs::write(images.join("anim.apng"), "apng").unwrap();
+        fs::write(images.join("still.png"), "png").unwrap();
+
+        let mut config = Config::default();
+        config.paths.image_dir = images.to_string_lossy().into_owned();
+        config.paths.image_cache = dir.path().join("image.json").to_string_lossy().into_owned();
+        config
+            .extension_engine
+            .insert(".APNG".to_string(), "mpvpaper".to_string());
+        let manager = WallManager::try_new(config.clone(), WallpaperMode::Image, false).unwrap();
+
+        assert_eq!(manager.wallpapers.len(), 2);
+        assert_eq!(
+            manager.route(&images.join("anim.apng")).as_deref(),
+            Some("mpvpaper")
+        );
+        assert_eq!(manager.route(&images.join("still.png")), None);
+
+        config
+            .extension_engine
+            .insert("webp".to_string(), "feh".to_string());
+        let err = WallManager::try_new(config, WallpaperMode::Image, false)
+            .err()
+            .unwrap();
+        assert!(err.contains("extension_engine.webp"));
+    }
+
+    #[test]
+    fn test_size_penalty_prefers_smaller_files() {
+        const GB: u64 = 1024 * 1024 * 1024;
+        let wall = |name: &str, value: f64| Wallpaper {
+            path: PathBuf::from(name),
+            value,
+            ..Default::default()
+        };
+        let mut pool = vec![
+            wall("4k.mp4", 110.0),
+            wall("1080p.mp4", 105.0),
+            wall("missing.mp4", 100.0),
+            wall("huge.mp4", 20.0),
+        ];
+        apply_size_penalty(&mut pool, 5.0, |path| match path.to_str() {
+            Some("4k.mp4") => Some(4 * GB),
+            Some("1080p.mp4") => Some(GB / 2),
+            Some("huge.mp4") => Some(20 * GB),
+            _ => None,
+        });
+
+        let values: Vec<f64> = pool.iter().map(|w| w.value).collect();
+        assert_eq!(values, vec![90.0, 102.5, 100.0, 1.0]);
+    }
+
+    #[test]
+    fn test_long_videos_are_skipped_using_cached_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, mut config) = test_config(dir.path(), &[]);
+        let movie = videos.join("movie.mp4");
+        fs::write(&movie, "movie").unwrap();
+
+        let cache = dir.path().join("video.json");
+        let entry = Wallpaper {
+            path: movie.clone(),
+            value: 100.0,
+            duration_secs: Some(7200.0),
+            ..Default::default()
+        };
+        fs::write(&cache, serde_json::to_string(&vec![entry]).unwrap()).unwrap();
+
+        config.video_engine.max_video_seconds = 600;
+        let manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+
+        assert!(manager.wallpapers.is_empty());
+        // 被跳过的条目连同时长保留在缓存中，下次扫描无需重新读取
+        let saved = fs::read_to_string(&cache).unwrap();
+        assert!(saved.contains("movie.mp4") && saved.contains("7200"));
+    }
+
+    #[test]
+    fn test_play_stats_tolerates_nan_weight() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = test_manager(dir.path(), &["a.mp4", "b.mp4"]);
+        // 手动编辑或损坏的缓存中可能出现 NaN
+        manager.wallpapers[0].value = f64::NAN;
+
+        assert_eq!(manager.play_stats().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_probe_file_detects_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.mp4");
+        let clip = dir.path().join("clip.mp4");
+        fs::write(&empty, "").unwrap();
+        fs::write(&clip, "\0\0\0\x18ftypmp42").unwrap();
+
+        assert!(probe_file(&empty).is_err());
+        assert!(probe_file(&clip).is_ok());
+        assert!(probe_file(&dir.path().join("missing.mp4")).is_err());
+    }
+
+    #[test]
+    fn test_sort_key_orders() {
+        let wall = |name: &str, value: f64, skip_streak: u32, last_played: Option<u64>| Wallpaper {
+            path: PathBuf::from(name),
+            value,
+            skip_streak,
+            last_played,
+            ..Default::default()
+        };
+        let walls = vec![
+            wall("b.mp4", 120.0, 0, Some(300)),
+            wall("c.mp4", 100.0, 5, None),
+            wall("a.mp4", 80.0, 2, Some(100)),
+        ];
+        let order = |key: SortKey| {
+            let mut sorted = walls.clone();
+            sorted.sort_by(|a, b| key.compare(a, b));
+            sorted
+                .iter()
+                .map(|w| w.path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(SortKey::Value), ["b.mp4", "c.mp4", "a.mp4"]);
+        assert_eq!(order(SortKey::Skips), ["c.mp4", "a.mp4", "b.mp4"]);
+        assert_eq!(order(SortKey::Name), ["a.mp4", "b.mp4", "c.mp4"]);
+        assert_eq!(order(SortKey::Recent), ["b.mp4", "a.mp4", "c.mp4"]);
+    }
+
+    #[test]
+    fn test_moved_file_keeps_weight() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, config) = test_config(dir.path(), &[]);
+        fs::create_dir_all(videos.join("old")).unwrap();
+        fs::create_dir_all(videos.join("new")).unwrap();
+
+        let old_path = videos.join("old/clip.mp4");
+        let new_path = videos.join("new/clip.mp4");
+        fs::write(&old_path, "clip content").unwrap();
+        let hash = content_hash(&old_path);
+        assert!(hash.is_some());
+        fs::rename(&old_path, &new_path).unwrap();
+        assert_eq!(content_hash(&new_path), hash);
+
+        let cache = dir.path().join("video.json");
+        let entry = Wallpaper {
+            path: old_path,
+            value: 42.0,
+            skip_streak: 3,
+            play_count: 7,
+            content_hash: hash,
+            ..Default::default()
+        };
+        fs::write(&cache, serde_json::to_string(&vec![entry]).unwrap()).unwrap();
+
+        let manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+
+        assert_eq!(manager.wallpapers.len(), 1);
+        let wall = &manager.wallpapers[0];
+        assert_eq!(wall.path, new_path);
+        assert_eq!(wall.play_count, 7);
+        assert_eq!(wall.skip_streak, 3);
+    }
+
+    #[test]
+    fn test_scan_hashes_only_when_files_moved() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = test_manager(dir.path(), &["a.mp4", "b.mp4"]);
+        assert!(manager.wallpapers.iter().all(|w| w.content_hash.is_none()));
+
+        // 播放后记录内容标识
+        manager.update_weights_many(&[0]);
+        let played = &manager.wallpapers[0];
+        assert_eq!(played.content_hash, content_hash(&played.path));
+        assert!(manager.wallpapers[1].content_hash.is_none());
+    }
+
+    #[test]
+    fn test_prefer_new_files_boosts_new_wallpaper() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, mut config) = test_config(dir.path(), &[]);
+        let old_path = videos.join("old.mp4");
+        let new_path = videos.join("new.mp4");
+        fs::write(&old_path, "old").unwrap();
+        fs::write(&new_path, "new").unwrap();
+
+        let cache = dir.path().join("video.json");
+        let entry = Wallpaper {
+            path: old_path,
+            value: 100.0,
+            ..Default::default()
+        };
+        fs::write(&cache, serde_json::to_string(&vec![entry]).unwrap()).unwrap();
+
+        config.weight.prefer_new_files = true;
+        let manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+
+        let new = manager
+            .wallpapers
+            .iter()
+            .find(|w| w.path == new_path)
+            .unwrap();
+        assert!(new.value >= 100.0 + NEW_FILE_BOOST_ROUNDS * 10.0);
+    }
+
+    #[test]
+    fn test_scan_index_used_until_directory_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, config) = test_config(dir.path(), &["a.mp4"]);
+
+        let manager = WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+        assert_eq!(manager.wallpapers.len(), 1);
+
+        // 往索引中加入一个不存在的文件：目录未变化时应直接信任索引
+        let index_path = manager.index_path();
+        let mut index: ScanIndex =
+            serde_json::from_str(&fs::read_to_string(&index_path).unwrap()).unwrap();
+        index.files.push(IndexedFile {
+            path: videos.join("ghost.mp4"),
+            added: SystemTime::UNIX_EPOCH,
+            tags: Vec::new(),
+            sidecar_modified: None,
+        });
+        fs::write(&index_path, serde_json::to_string(&index).unwrap()).unwrap();
+
+        let mut manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+        assert_eq!(manager.wallpapers.len(), 2);
+
+        // reset 总是完整遍历
+        manager.reset();
+        assert_eq!(manager.wallpapers.len(), 1);
+        assert_eq!(manager.wallpapers[0].path, videos.join("a.mp4"));
+    }
+
+    #[test]
+    fn test_scan_index_rereads_tags_edited_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, config) = test_config(dir.path(), &[]);
+        let clip = videos.join("a.mp4");
+        fs::write(&clip, "a").unwrap();
+        let tags_path = path_with_suffix(&clip, ".tags");
+        fs::write(&tags_path, "nature").unwrap();
+
+        let manager = WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+        assert_eq!(manager.wallpapers[0].tags, vec!["nature"]);
+
+        // 原地编辑不更新目录的修改时间，索引仍然有效，但标签应重新读取
+        let dir_mtime = fs::metadata(&videos).unwrap().modified().unwrap();
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&tags_path)
+            .unwrap();
+        (&file).write_all(b"anime").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        drop(file);
+        assert_eq!(
+            fs::metadata(&videos).unwrap().modified().unwrap(),
+            dir_mtime
+        );
+
+        let manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+        assert!(manager.load_index(&videos).is_some());
+        assert_eq!(manager.wallpapers[0].tags, vec!["anime"]);
+    }
+
+    #[test]
+    fn test_skip_hidden_ignores_dot_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, mut config) = test_config(dir.path(), &["a.mp4", ".b.mp4"]);
+        fs::create_dir_all(videos.join(".thumbnails")).unwrap();
+        fs::write(videos.join(".thumbnails").join("c.mp4"), "c").unwrap();
+
+        let manager = WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+        assert_eq!(manager.wallpapers.len(), 1);
+        assert_eq!(manager.wallpapers[0].path, videos.join("a.mp4"));
+
+        // 关闭后扫描参数变化，索引失效，隐藏文件重新加入
+        config.paths.skip_hidden = false;
+        let manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+        assert_eq!(manager.wallpapers.len(), 3);
+    }
+
+    #[test]
+    fn test_verify_cache_reports_and_fixes_stale_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, config) = test_config(dir.path(), &["a.mp4", "new.mp4"]);
+        fs::write(videos.join("empty.mp4"), "").unwrap();
+
+        let entry = |name: &str| Wallpaper {
+            path: videos.join(name),
+            value: 100.0,
+            ..Default::default()
+        };
+        let cache = dir.path().join("video.json");
+        let entries = vec![entry("a.mp4"), entry("empty.mp4"), entry("gone.mp4")];
+        fs::write(&cache, serde_json::to_string(&entries).unwrap()).unwrap();
+
+        let report = verify_cache(&config, WallpaperMode::Video, false).unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.missing, [videos.join("gone.mp4")]);
+        assert_eq!(report.unreadable.len(), 1);
+        assert_eq!(report.unreadable[0].0, videos.join("empty.mp4"));
+        assert_eq!(report.untracked, [videos.join("new.mp4")]);
+        assert!(!report.fixed);
+
+        let report = verify_cache(&config, WallpaperMode::Video, true).unwrap();
+        assert!(report.fixed);
+        let kept = parse_cache(&fs::read_to_string(&cache).unwrap())
+            .unwrap()
+            .wallpapers;
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].path, videos.join("a.mp4"));
+    }
+
+    #[test]
+    fn test_dry_run_next_leaves_cache_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, mut config) = test_config(dir.path(), &["a.mp4", "b.mp4"]);
+
+        // 旧格式缓存、待衰减的权重和未记录的新文件，正常加载时都会改写缓存
+        let cache = dir.path().join("video.json");
+        let entry = Wallpaper {
+            path: videos.join("a.mp4"),
+            value: 150.0,
+            last_played: Some(0),
+            play_count: 1,
+            ..Default::default()
+        };
+        fs::write(&cache, serde_json::to_string(&vec![entry]).unwrap()).unwrap();
+        let before = fs::read(&cache).unwrap();
+        let mtime = fs::metadata(&cache).unwrap().modified().unwrap();
+
+        config.weight.decay_per_hour = 1.0;
+        let mut manager = WallManager::try_new(config, WallpaperMode::Video, true).unwrap();
+        assert_eq!(manager.wallpapers.len(), 2);
+        manager.next().unwrap();
+        manager.flush();
+
+        assert_eq!(fs::read(&cache).unwrap(), before);
+        assert_eq!(fs::metadata(&cache).unwrap().modified().unwrap(), mtime);
+        assert!(!manager.index_path().exists());
+    }
+
+    /// 总是显示失败的引擎，记录尝试显示的文件；corrupt 时先清空文件，模拟文件损坏
+    struct FailingEngine {
+        attempts: std::rc::Rc<std::cell::RefCell<Vec<PathBuf>>>,
+        corrupt: bool,
+    }
+
+    impl PaperEngine for FailingEngine {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn set_wallpaper(&self, path: &Path) -> Result<(), String> {
+            self.attempts.borrow_mut().push(path.to_path_buf());
+            if self.corrupt {
+                fs::write(path, "").unwrap();
+                return Err("无法解码".to_string());
+            }
+            Err("swww-daemon 在 3 秒内未就绪".to_string())
+        }
+
+        fn stop(&self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    /// 用 FailingEngine 切换一次，返回错误信息、尝试显示的文件和被标记为损坏的文件
+    fn switch_with_failing_engine(corrupt: bool) -> (String, Vec<PathBuf>, Vec<PathBuf>) {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, config) = test_config(dir.path(), &[]);
+        // 写入有效的图片内容，安装了 ffprobe 时也能通过内容检查
+        for name in ["a.mp4", "b.mp4", "c.mp4", "d.mp4", "e.mp4"] {
+            image::RgbImage::new(2, 2)
+                .save_with_format(videos.join(name), image::ImageFormat::Png)
+                .unwrap();
+        }
+
+        let mut manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+        let attempts = std::rc::Rc::default();
+        manager.engine = Box::new(FailingEngine {
+            attempts: std::rc::Rc::clone(&attempts),
+            corrupt,
+        });
+
+        let err = manager.next().unwrap_err();
+        let broken = manager
+            .wallpapers
+            .iter()
+            .filter(|w| w.broken)
+            .map(|w| w.path.clone())
+            .collect();
+        (err, attempts.take(), broken)
+    }
+
+    #[test]
+    fn test_corrupt_file_marked_broken_and_retried() {
+        let (err, attempts, broken) = switch_with_failing_engine(true);
+        assert!(err.contains("无法解码"), "{}", err);
+
+        // 每次换一张不同的壁纸，尝试次数有上限
+        assert_eq!(attempts.len(), MAX_SWITCH_ATTEMPTS);
+        assert_eq!(broken.len(), MAX_SWITCH_ATTEMPTS);
+        assert!(attempts.iter().all(|p| broken.contains(p)));
+    }
+
+    #[test]
+    fn test_engine_failure_does_not_blame_file() {
+        let (err, attempts, broken) = switch_with_failing_engine(false);
+        assert!(err.contains("未就绪"), "{}", err);
+        assert_eq!(attempts.len(), 1);
+        assert!(broken.is_empty());
+    }
+
+    #[test]
+    fn test_unreadable_cache_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, config) = test_config(dir.path(), &["a.mp4"]);
+        // 缓存路径是目录时读取失败，但这不是缓存损坏
+        let cache = dir.path().join("video.json");
+        fs::create_dir_all(&cache).unwrap();
+
+        let manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+
+        assert_eq!(manager.wallpapers.len(), 1);
+        assert!(manager.save().is_err());
+        assert!(cache.is_dir());
+        assert!(!path_with_suffix(&cache, ".bak").exists());
+    }
+
+    #[test]
+    fn test_failed_switch_keeps_routed_engine_active() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = test_manager(dir.path(), &["a.mp4"]);
+        let failing = || FailingEngine {
+            attempts: std::rc::Rc::default(),
+            corrupt: false,
+        };
+        manager.engine = Box::new(failing());
+        manager
+            .routed_engines
+            .insert("mpvpaper".to_string(), Box::new(failing()));
+        manager.routed_active = Some("mpvpaper".to_string());
+
+        // 默认引擎显示失败时，仍在运行的路由引擎不能被遗忘
+        let wallpaper = manager.wallpapers[0].clone();
+        assert!(manager.set_wallpaper(&wallpaper).is_err());
+        assert_eq!(manager.routed_active.as_deref(), Some("mpvpaper"));
+    }
+
+    #[test]
+    fn test_session_no_repeat_excludes_recent_picks() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, mut config) = test_config(dir.path(), &["a.mp4", "b.mp4", "c.mp4"]);
+        config.weight.session_no_repeat = 2;
+        let mut manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+
+        let mut picked: Vec<PathBuf> = Vec::new();
+        for _ in 0..6 {
+            let wallpaper = manager.pick_next().unwrap();
+            assert!(!picked.iter().rev().take(2).any(|p| *p == wallpaper.path));
+            let idx = manager
+                .wallpapers
+                .iter()
+                .position(|w| w.path == wallpaper.path)
+                .unwrap();
+            manager.update_weights(idx);
+            picked.push(wallpaper.path);
+        }
+    }
+
+    #[test]
+    fn test_selection_strategies_ignore_weights() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, mut config) = test_config(dir.path(), &["b.mp4", "a.mp4", "c.mp4"]);
+        config.weight.selection_strategy = SelectionStrategy::Sequential;
+        let mut manager =
+            WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            let wallpaper = manager.pick_next().unwrap();
+            let idx = manager
+                .wallpapers
+                .iter()
+                .position(|w| w.path == wallpaper.path)
+                .unwrap();
+            manager.update_weights(idx);
+            order.push(wallpaper.path.file_name().unwrap().to_owned());
+        }
+        assert_eq!(order, ["a.mp4", "b.mp4", "c.mp4", "a.mp4"]);
+
+        config.weight.selection_strategy = SelectionStrategy::Shuffle;
+        let mut manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+        for _ in 0..10 {
+            let last = manager.last_path.clone();
+            let wallpaper = manager.pick_next().unwrap();
+            assert_ne!(Some(&wallpaper.path), last.as_ref());
+            let idx = manager
+                .wallpapers
+                .iter()
+                .position(|w| w.path == wallpaper.path)
+                .unwrap();
+            manager.update_weights(idx);
+        }
+    }
+
+    #[test]
+    fn test_sequential_cursor_persists_and_follows_directory_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, mut config) = test_config(dir.path(), &["a.mp4", "c.mp4", "e.mp4"]);
+        config.weight.selection_strategy = SelectionStrategy::Sequential;
+
+        let play = |manager: &mut WallManager| {
+            let wallpaper = manager.pick_next().unwrap();
+            let idx = manager
+                .wallpapers
+                .iter()
+                .position(|w| w.path == wallpaper.path)
+                .unwrap();
+            manager.update_weights(idx);
+            manager.save().unwrap();
+            wallpaper.path.file_name().unwrap().to_owned()
+        };
+
+        let mut manager =
+            WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+        assert_eq!(play(&mut manager), "a.mp4");
+        assert_eq!(play(&mut manager), "c.mp4");
+
+        // 重启后从上次的位置继续；删除当前文件并在前后加入新文件
+        fs::remove_file(videos.join("c.mp4")).unwrap();
+        fs::write(videos.join("b.mp4"), "b").unwrap();
+        fs::write(videos.join("d.mp4"), "d").unwrap();
+        let mut manager =
+            WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+        assert_eq!(play(&mut manager), "d.mp4");
+        assert_eq!(play(&mut manager), "e.mp4");
+        assert_eq!(play(&mut manager), "a.mp4");
+        assert_eq!(play(&mut manager), "b.mp4");
+
+        // hard_reset 丢弃缓存，顺序从头开始
+        manager.hard_reset().unwrap();
+        assert_eq!(play(&mut manager), "a.mp4");
+    }
+
+    #[test]
+    fn test_same_content_compares_beyond_hashed_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut content = vec![7u8; CONTENT_HASH_BYTES as usize + 10];
+        let (a, b, c) = (
+            dir.path().join("a.mp4"),
+            dir.path().join("b.mp4"),
+            dir.path().join("c.mp4"),
+        );
+        fs::write(&a, &content).unwrap();
+        fs::write(&b, &content).unwrap();
+        *content.last_mut().unwrap() = 8;
+        fs::write(&c, &content).unwrap();
+
+        // 内容标识相同，但只有 a 和 b 完全相同
+        assert_eq!(content_hash(&a), content_hash(&c));
+        assert!(same_content(&a, &b).unwrap());
+        assert!(!same_content(&a, &c).unwrap());
+        assert!(same_content(&a, &dir.path().join("missing.mp4")).is_err());
+    }
+
+    #[test]
+    fn test_dedup_marks_copies_until_kept_file_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (videos, config) = test_config(dir.path(), &[]);
+        fs::write(videos.join("a.mp4"), "same").unwrap();
+        fs::write(videos.join("b.mp4"), "same").unwrap();
+        fs::write(videos.join("c.mp4"), "other").unwrap();
+
+        let mut manager =
+            WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+
+        let groups = manager.find_duplicates();
+        assert_eq!(groups, [vec![videos.join("a.mp4"), videos.join("b.mp4")]]);
+        assert_eq!(manager.mark_duplicates(&groups).unwrap(), 1);
+        assert_eq!(manager.mark_duplicates(&groups).unwrap(), 0);
+
+        // 标记保存在缓存中，重新加载后副本仍不会被选中
+        let mut manager =
+            WallManager::try_new(config.clone(), WallpaperMode::Video, false).unwrap();
+        for _ in 0..10 {
+            assert_ne!(manager.pick_next().unwrap().path, videos.join("b.mp4"));
         }
 
-        let content = serde_json::to_string_pretty(&self.wallpapers).expect("序列化失败");
-        fs::write(&cache_path, content).expect("无法写入缓存文件");
+        // 保留的文件被删除后副本重新参与轮换
+        fs::remove_file(videos.join("a.mp4")).unwrap();
+        let mut manager = WallManager::try_new(config, WallpaperMode::Video, false).unwrap();
+        let picked: HashSet<PathBuf> = (0..20).map(|_| manager.pick_next().unwrap().path).collect();
+        assert!(picked.contains(&videos.join("b.mp4")));
+        assert!(manager.find_duplicates().is_empty());
+        assert_eq!(manager.clear_duplicates().unwrap(), 1);
     }
 }
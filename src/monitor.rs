@@ -0,0 +1,139 @@
+/// 显示器（输出）枚举模块
+///
+/// 依次尝试 hyprctl（Hyprland）、wlr-randr（wlroots 合成器）、swaymsg（sway），
+/// 使用第一个成功返回输出列表的工具
+use std::process::Command;
+
+/// 输出查询命令（程序, 参数）
+const OUTPUT_QUERIES: &[(&str, &[&str])] = &[
+    ("hyprctl", &["monitors", "-j"]),
+    ("wlr-randr", &["--json"]),
+    ("swaymsg", &["-t", "get_outputs", "--raw"]),
+];
+
+/// 获取所有已连接输出的名称（如 "DP-1"、"HDMI-A-1"）
+///
+/// 无法获取时返回空列表
+pub fn list_outputs() -> Vec<String> {
+    query_outputs(parse_outputs)
+}
+
+/// 获取所有输出中最大的分辨率（宽, 高），无法获取时返回 None
+///
+/// 多显示器分辨率不同时分别取最大的宽和高，保证缩放后的图片在任何输出上都不会被放大
+pub fn max_resolution() -> Option<(u32, u32)> {
+    query_outputs(parse_resolutions)
+        .into_iter()
+        .reduce(|(w1, h1), (w2, h2)| (w1.max(w2), h1.max(h2)))
+}
+
+/// 依次执行输出查询命令，返回第一个非空的解析结果
+fn query_outputs<T>(parse: fn(&str) -> Vec<T>) -> Vec<T> {
+    OUTPUT_QUERIES
+        .iter()
+        .find_map(|(program, args)| {
+            let output = Command::new(program).args(*args).output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            let parsed = parse(&String::from_utf8_lossy(&output.stdout));
+            (!parsed.is_empty()).then_some(parsed)
+        })
+        .unwrap_or_default()
+}
+
+/// 解析输出列表 JSON 中已启用的输出
+///
+/// hyprctl、wlr-randr 与 swaymsg 都返回对象数组；
+/// wlr-randr 的 `enabled` 与 swaymsg 的 `active` 为 false 时表示输出已关闭，跳过
+fn enabled_outputs(json: &str) -> Vec<serde_json::Value> {
+    let monitors: Vec<serde_json::Value> = serde_json::from_str(json).unwrap_or_default();
+    monitors
+        .into_iter()
+        .filter(|m| {
+            ["enabled", "active"]
+                .iter()
+                .all(|key| m.get(key).and_then(|v| v.as_bool()) != Some(false))
+        })
+        .collect()
+}
+
+/// 解析输出名称（`name` 字段）
+fn parse_outputs(json: &str) -> Vec<String> {
+    enabled_outputs(json)
+        .iter()
+        .filter_map(|m| m.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// 解析输出分辨率
+///
+/// hyprctl 在顶层给出 `width` / `height`，swaymsg 在 `current_mode` 中给出，
+/// wlr-randr 在 `modes` 中以 `current: true` 标记当前模式
+fn parse_resolutions(json: &str) -> Vec<(u32, u32)> {
+    let size = |v: &serde_json::Value| {
+        let width = v.get("width")?.as_u64()?;
+        let height = v.get("height")?.as_u64()?;
+        Some((width as u32, height as u32))
+    };
+
+    enabled_outputs(json)
+        .iter()
+        .filter_map(|m| {
+            size(m)
+                .or_else(|| m.get("current_mode").and_then(size))
+                .or_else(|| {
+                    m.get("modes")?
+                        .as_array()?
+                        .iter()
+                        .find(|mode| mode.get("current").and_then(|c| c.as_bool()) == Some(true))
+                        .and_then(size)
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hyprctl_monitors() {
+        let json = r#"[
+            {"id": 0, "name": "DP-1", "width": 2560, "height": 1440},
+            {"id": 1, "name": "HDMI-A-1", "width": 1920, "height": 1080}
+        ]"#;
+        assert_eq!(parse_outputs(json), vec!["DP-1", "HDMI-A-1"]);
+        assert!(parse_outputs("not json").is_empty());
+    }
+
+    #[test]
+    fn test_parse_outputs_skips_disabled() {
+        let wlr_randr = r#"[
+            {"name": "eDP-1", "enabled": true},
+            {"name": "HDMI-A-1", "enabled": false}
+        ]"#;
+        assert_eq!(parse_outputs(wlr_randr), vec!["eDP-1"]);
+
+        let swaymsg = r#"[
+            {"name": "DP-2", "active": false},
+            {"name": "DP-3", "active": true}
+        ]"#;
+        assert_eq!(parse_outputs(swaymsg), vec!["DP-3"]);
+    }
+
+    #[test]
+    fn test_parse_resolutions() {
+        let hyprctl = r#"[{"name": "DP-1", "width": 2560, "height": 1440}]"#;
+        assert_eq!(parse_resolutions(hyprctl), vec![(2560, 1440)]);
+
+        let swaymsg = r#"[{"name": "DP-1", "active": true, "current_mode": {"width": 3840, "height": 2160}}]"#;
+        assert_eq!(parse_resolutions(swaymsg), vec![(3840, 2160)]);
+
+        let wlr_randr = r#"[{"name": "eDP-1", "enabled": true, "modes": [
+            {"width": 1280, "height": 720, "current": false},
+            {"width": 1920, "height": 1080, "current": true}
+        ]}]"#;
+        assert_eq!(parse_resolutions(wlr_randr), vec![(1920, 1080)]);
+    }
+}
@@ -0,0 +1,211 @@
+/// 静态壁纸预缩放模块
+///
+/// 将超过屏幕分辨率的图片缩放到刚好覆盖屏幕的尺寸，结果按源文件路径、
+/// 修改时间、大小和目标分辨率缓存，源文件变化后自动重新生成
+use crate::manager::fnv1a;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageReader};
+use std::fs;
+use std::io::BufWriter;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 缓存图片的 JPEG 质量
+const JPEG_QUALITY: u8 = 90;
+
+/// 获取缩放后的图片路径
+///
+/// 图片未超过屏幕分辨率时返回 None（直接使用原图）；
+/// 已有缓存时直接返回，否则在磁盘剩余空间不低于 min_free_mb 时缩放并写入 cache_dir
+pub fn get_or_resize_image(
+    path: &Path,
+    cache_dir: &Path,
+    screen: (u32, u32),
+    min_free_mb: u64,
+) -> Result<Option<PathBuf>, String> {
+    let reader = |p: &Path| {
+        ImageReader::open(p)
+            .and_then(|r| r.with_guessed_format())
+            .map_err(|e| format!("无法读取 {}: {}", p.display(), e))
+    };
+
+    // 只读取文件头获取尺寸，无需缩放时不解码整张图片
+    let size = reader(path)?
+        .into_dimensions()
+        .map_err(|e| format!("无法读取图片尺寸 {}: {}", path.display(), e))?;
+    let Some((width, height)) = cover_size(size, screen) else {
+        return Ok(None);
+    };
+
+    let key = cache_key(path, screen)?;
+    if let Some(cached) = ["jpg", "png"]
+        .iter()
+        .map(|ext| cache_dir.join(format!("{}.{}", key, ext)))
+        .find(|p| p.is_file())
+    {
+        return Ok(Some(cached));
+    }
+
+    if min_free_mb > 0
+        && let Some(free_mb) = free_space_mb(cache_dir)
+        && free_mb < min_free_mb
+    {
+        return Err(format!(
+            "缓存所在磁盘剩余 {} MB，低于 min_free_mb（{} MB），跳过缩放",
+            free_mb, min_free_mb
+        ));
+    }
+
+    log::info!(
+        "缩放图片 {} ({}x{} → {}x{})",
+        path.display(),
+        size.0,
+        size.1,
+        width,
+        height
+    );
+    let image = reader(path)?
+        .decode()
+        .map_err(|e| format!("无法解码 {}: {}", path.display(), e))?;
+    let resized = image.resize_exact(width, height, FilterType::Lanczos3);
+
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("无法创建缓存目录 {}: {}", cache_dir.display(), e))?;
+
+    // 带透明通道的图片保存为 PNG，其余保存为 JPEG；先写临时文件再重命名，避免留下半张图片
+    let has_alpha = resized.color().has_alpha();
+    let ext = if has_alpha { "png" } else { "jpg" };
+    let target = cache_dir.join(format!("{}.{}", key, ext));
+    let tmp = cache_dir.join(format!("{}.tmp", key));
+
+    let written = if has_alpha {
+        resized
+            .save_with_format(&tmp, ImageFormat::Png)
+            .map_err(|e| e.to_string())
+    } else {
+        fs::File::create(&tmp)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                let encoder = JpegEncoder::new_with_quality(BufWriter::new(file), JPEG_QUALITY);
+                resized
+                    .to_rgb8()
+                    .write_with_encoder(encoder)
+                    .map_err(|e| e.to_string())
+            })
+    };
+    if let Err(e) = written.and_then(|_| fs::rename(&tmp, &target).map_err(|e| e.to_string())) {
+        fs::remove_file(&tmp).ok();
+        return Err(format!("无法写入缓存 {}: {}", target.display(), e));
+    }
+
+    Ok(Some(target))
+}
+
+/// 计算等比缩放后刚好覆盖屏幕的尺寸，图片不大于该尺寸时返回 None
+fn cover_size((width, height): (u32, u32), (screen_w, screen_h): (u32, u32)) -> Option<(u32, u32)> {
+    if width == 0 || height == 0 || screen_w == 0 || screen_h == 0 {
+        return None;
+    }
+
+    let scale = f64::max(
+        screen_w as f64 / width as f64,
+        screen_h as f64 / height as f64,
+    );
+    if scale >= 1.0 {
+        return None;
+    }
+
+    let scaled = |len: u32, min: u32| ((len as f64 * scale).round() as u32).max(min);
+    Some((scaled(width, screen_w), scaled(height, screen_h)))
+}
+
+/// 查询目录所在文件系统的剩余空间（MB）
+///
+/// 目录尚未创建时查询最近的已存在的上级目录，查询失败时返回 None
+fn free_space_mb(dir: &Path) -> Option<u64> {
+    let existing = dir.ancestors().find(|p| p.exists())?;
+    let stat = nix::sys::statvfs::statvfs(existing).ok()?;
+    let free_bytes = stat.blocks_available() as u64 * stat.fragment_size() as u64;
+    Some(free_bytes / 1024 / 1024)
+}
+
+/// 缓存文件名：由源文件路径、修改时间、大小和目标分辨率计算
+///
+/// 使用固定的 FNV-1a 哈希，升级 Rust 版本后已有的缓存文件仍然有效
+fn cache_key(path: &Path, screen: (u32, u32)) -> Result<String, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("无法读取 {}: {}", path.display(), e))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    Ok(hash_key(path, mtime, metadata.len(), screen))
+}
+
+/// 按固定的字节序拼接缓存文件名的各项参数并计算哈希
+fn hash_key(path: &Path, mtime: u64, len: u64, (width, height): (u32, u32)) -> String {
+    // 路径后加 0 分隔，避免路径结尾与后面的数值混淆
+    let mut bytes = path.as_os_str().as_bytes().to_vec();
+    bytes.push(0);
+    bytes.extend_from_slice(&mtime.to_le_bytes());
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(&width.to_le_bytes());
+    bytes.extend_from_slice(&height.to_le_bytes());
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cover_size() {
+        // 8000x6000 覆盖 1920x1080：按宽度缩放 0.24 → 1920x1440
+        assert_eq!(cover_size((8000, 6000), (1920, 1080)), Some((1920, 1440)));
+        // 超宽图片按高度缩放
+        assert_eq!(cover_size((10000, 2160), (1920, 1080)), Some((5000, 1080)));
+        // 未超过屏幕分辨率时不缩放
+        assert_eq!(cover_size((1920, 1080), (1920, 1080)), None);
+        assert_eq!(cover_size((4000, 1000), (1920, 1080)), None);
+    }
+
+    #[test]
+    fn test_resize_is_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("big.png");
+        let cache = dir.path().join("resized");
+        image::RgbImage::new(400, 300).save(&source).unwrap();
+
+        let resized = get_or_resize_image(&source, &cache, (200, 100), 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(image::image_dimensions(&resized).unwrap(), (200, 150));
+        assert_eq!(
+            get_or_resize_image(&source, &cache, (200, 100), 0).unwrap(),
+            Some(resized)
+        );
+        assert_eq!(
+            get_or_resize_image(&source, &cache, (800, 600), 0).unwrap(),
+            None
+        );
+
+        // 剩余空间不足时拒绝生成新的缩放图片
+        assert!(free_space_mb(&cache).is_some());
+        assert!(get_or_resize_image(&source, &cache, (100, 50), u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_cache_key_is_stable() {
+        // 固定的期望值：哈希算法变化会使所有已有的缓存文件失效
+        assert_eq!(
+            hash_key(Path::new("/walls/a.png"), 1_700_000_000, 4096, (2560, 1440)),
+            "dbf260e1c3217bbc"
+        );
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,278 @@
+/// 配色提取模块
+///
+/// 启用 `palette` feature 时，切换壁纸后从图片（动态壁纸先用 ffmpeg 截取一帧）中
+/// 提取主色调，写入 JSON 配色文件并执行 [palette] 中的命令；未启用时只提示一次
+///
+/// 截取视频画面和量化颜色较慢，提取在后台线程中进行，不阻塞壁纸切换
+use crate::config::{Config, PaletteConfig};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once, PoisonError};
+use std::thread::{self, JoinHandle};
+
+/// 未编译 palette feature 时只提示一次
+static UNAVAILABLE_WARNING: Once = Once::new();
+
+/// 最近一次请求提取的序号，开始提取前已有更新的请求时放弃本次提取
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 同一时间只运行一个提取任务（截取画面的临时文件按进程命名）
+static RUNNING: Mutex<()> = Mutex::new(());
+
+/// 最近一次提取任务，见 `wait`
+static PENDING: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// 在后台提取壁纸配色并写入配色文件，失败只记录日志
+pub fn export(config: &PaletteConfig, wallpaper: &Path) {
+    if !config.enabled {
+        return;
+    }
+    if !imp::AVAILABLE {
+        UNAVAILABLE_WARNING.call_once(|| {
+            log::warn!("[palette] 已启用，但当前版本未使用 palette feature 编译，不会提取配色");
+        });
+        return;
+    }
+
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let config = config.clone();
+    let wallpaper = wallpaper.to_path_buf();
+    let handle = thread::spawn(move || {
+        let _running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+        // 快速连续切换时只为最后一张壁纸提取配色
+        if GENERATION.load(Ordering::SeqCst) == generation {
+            extract(&config, &wallpaper);
+        }
+    });
+    *PENDING.lock().unwrap_or_else(PoisonError::into_inner) = Some(handle);
+}
+
+/// 等待后台的配色提取完成（单次命令退出前调用，否则进程退出时提取会被中断）
+pub fn wait() {
+    let pending = PENDING
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+    if let Some(handle) = pending {
+        handle.join().ok();
+    }
+}
+
+/// 提取配色并写入配色文件
+fn extract(config: &PaletteConfig, wallpaper: &Path) {
+    let output = Config::expand_path(&config.output);
+    match imp::export(wallpaper, config.colors, &output) {
+        Ok(colors) => {
+            log::debug!("配色已写入 {}: {}", output.display(), colors.join(" "));
+            run_command(config, &output, wallpaper);
+        }
+        Err(e) => log::warn!("提取配色失败 ({}): {}", wallpaper.display(), e),
+    }
+}
+
+/// 写入配色文件后在后台执行 on_extract_command
+fn run_command(config: &PaletteConfig, output: &Path, wallpaper: &Path) {
+    let Some(command) = config
+        .on_extract_command
+        .as_deref()
+        .filter(|c| !c.trim().is_empty())
+    else {
+        return;
+    };
+
+    let spawned = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("LIANWALL_PALETTE", output)
+        .env("LIANWALL_PATH", wallpaper)
+        .spawn();
+    match spawned {
+        Ok(mut child) => {
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => log::warn!("配色命令异常退出: {}", status),
+                Err(e) => log::warn!("等待配色命令失败: {}", e),
+                Ok(_) => {}
+            });
+        }
+        Err(e) => log::warn!("无法执行配色命令: {}", e),
+    }
+}
+
+#[cfg(feature = "palette")]
+mod imp {
+    use crate::manager::write_atomic;
+    use color_quant::NeuQuant;
+    use image::{DynamicImage, ImageFormat};
+    use serde::Serialize;
+    use std::fs;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    pub const AVAILABLE: bool = true;
+
+    /// 量化时使用的颜色数（NeuQuant 要求不少于 64）
+    const QUANTIZE_COLORS: usize = 64;
+    /// 提取前将图片缩小到的最大边长，只影响速度
+    const SAMPLE_SIZE: u32 = 256;
+    /// 两个颜色的 RGB 距离小于此值时视为同一种颜色，只保留占比更高的
+    const MIN_DISTANCE: u32 = 24;
+
+    /// 配色文件内容
+    #[derive(Serialize)]
+    struct Palette<'a> {
+        wallpaper: &'a Path,
+        /// 按占比从高到低排列的颜色（#rrggbb）
+        colors: &'a [String],
+    }
+
+    pub fn export(wallpaper: &Path, count: usize, output: &Path) -> Result<Vec<String>, String> {
+        let image = load(wallpaper)?;
+        let colors: Vec<String> = dominant_colors(&image, count)
+            .into_iter()
+            .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+            .collect();
+
+        let content = serde_json::to_string_pretty(&Palette {
+            wallpaper,
+            colors: &colors,
+        })
+        .map_err(|e| e.to_string())?;
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // 其他程序可能随时读取配色文件，原子写入避免读到写了一半的内容
+        write_atomic(output, content.as_bytes())
+            .map_err(|e| format!("{}: {}", output.display(), e))?;
+        Ok(colors)
+    }
+
+    /// 读取图片，image 不支持的格式（视频、GIF）用 ffmpeg 截取一帧
+    fn load(path: &Path) -> Result<DynamicImage, String> {
+        if ImageFormat::from_path(path).is_ok() {
+            return image::open(path).map_err(|e| e.to_string());
+        }
+
+        let frame =
+            std::env::temp_dir().join(format!("lianwall-palette-{}.png", std::process::id()));
+        // thumbnail 滤镜从开头的若干帧中选出最有代表性的一帧，避开片头的黑场
+        let status = Command::new("ffmpeg")
+            .args(["-v", "error", "-y", "-i"])
+            .arg(path)
+            .args(["-vf", "thumbnail", "-frames:v", "1"])
+            .arg(&frame)
+            .stdin(Stdio::null())
+            .status()
+            .map_err(|e| format!("无法执行 ffmpeg: {}", e))?;
+        if !status.success() {
+            return Err(format!("ffmpeg 截取画面失败: {}", status));
+        }
+
+        let image = image::open(&frame).map_err(|e| e.to_string());
+        fs::remove_file(&frame).ok();
+        image
+    }
+
+    /// 提取最多 count 个主色调，按像素占比从高到低排列，相近的颜色只保留一个
+    pub fn dominant_colors(image: &DynamicImage, count: usize) -> Vec<[u8; 3]> {
+        // thumbnail 也会放大小图，只缩小超过 SAMPLE_SIZE 的图片
+        let mut pixels = if image.width() > SAMPLE_SIZE || image.height() > SAMPLE_SIZE {
+            image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE).to_rgba8()
+        } else {
+            image.to_rgba8()
+        }
+        .into_raw();
+        // 透明区域不参与统计颜色占比，按不透明处理
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+        if pixels.is_empty() {
+            return Vec::new();
+        }
+
+        let quant = NeuQuant::new(10, QUANTIZE_COLORS, &pixels);
+        let mut frequency = vec![0usize; QUANTIZE_COLORS];
+        for pixel in pixels.chunks_exact(4) {
+            frequency[quant.index_of(pixel)] += 1;
+        }
+
+        let map = quant.color_map_rgb();
+        let mut ranked: Vec<(usize, [u8; 3])> = map
+            .chunks_exact(3)
+            .enumerate()
+            .filter(|(i, _)| frequency[*i] > 0)
+            .map(|(i, rgb)| (frequency[i], [rgb[0], rgb[1], rgb[2]]))
+            .collect();
+        ranked.sort_by_key(|(frequency, _)| std::cmp::Reverse(*frequency));
+
+        let mut colors: Vec<[u8; 3]> = Vec::new();
+        for (_, color) in ranked {
+            if colors.len() >= count {
+                break;
+            }
+            if colors.iter().all(|c| distance(c, &color) >= MIN_DISTANCE) {
+                colors.push(color);
+            }
+        }
+        colors
+    }
+
+    /// RGB 空间中的欧氏距离
+    fn distance(a: &[u8; 3], b: &[u8; 3]) -> u32 {
+        let sum: u32 = a
+            .iter()
+            .zip(b)
+            .map(|(x, y)| (*x as i32 - *y as i32).pow(2) as u32)
+            .sum();
+        (sum as f64).sqrt() as u32
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use image::{Rgb, RgbImage};
+
+        #[test]
+        fn test_dominant_colors_ordered_by_share() {
+            // 3/4 红色，1/4 蓝色
+            let image = RgbImage::from_fn(64, 64, |x, _| {
+                if x < 48 {
+                    Rgb([220, 20, 20])
+                } else {
+                    Rgb([20, 20, 220])
+                }
+            });
+            let colors = dominant_colors(&DynamicImage::ImageRgb8(image), 8);
+
+            assert_eq!(colors.len(), 2);
+            assert!(colors[0][0] > 180 && colors[0][2] < 60);
+            assert!(colors[1][2] > 180 && colors[1][0] < 60);
+        }
+
+        #[test]
+        fn test_export_replaces_palette_file_atomically() {
+            let dir = tempfile::tempdir().unwrap();
+            let wallpaper = dir.path().join("red.png");
+            RgbImage::from_pixel(8, 8, Rgb([220, 20, 20]))
+                .save(&wallpaper)
+                .unwrap();
+            let output = dir.path().join("palette.json");
+            fs::write(&output, "old").unwrap();
+
+            let colors = export(&wallpaper, 4, &output).unwrap();
+            let content = fs::read_to_string(&output).unwrap();
+            assert!(content.contains(&colors[0]));
+            assert!(!dir.path().join("palette.json.tmp").exists());
+        }
+    }
+}
+
+#[cfg(not(feature = "palette"))]
+mod imp {
+    use std::path::Path;
+
+    pub const AVAILABLE: bool = false;
+
+    pub fn export(_wallpaper: &Path, _count: usize, _output: &Path) -> Result<Vec<String>, String> {
+        Err("未启用 palette feature".to_string())
+    }
+}
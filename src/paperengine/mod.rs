@@ -1,9 +1,19 @@
 #![allow(dead_code)]
 
 pub mod mpvpaper;
+pub mod swaybg;
 pub mod swww;
+pub mod wpaperd;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::vram;
+
+/// 无法查询显示状态的引擎在 wait_until_shown 中等待的时间
+const UNCONFIRMED_WAIT: Duration = Duration::from_secs(1);
 
 /// 壁纸引擎 trait，定义统一接口
 pub trait PaperEngine {
@@ -13,30 +23,162 @@ pub trait PaperEngine {
     /// 设置壁纸
     fn set_wallpaper(&self, path: &Path) -> Result<(), String>;
 
+    /// 在指定输出上设置壁纸（不影响其他输出），默认实现忽略输出名
+    fn set_wallpaper_on(&self, output: &str, path: &Path) -> Result<(), String> {
+        let _ = output;
+        self.set_wallpaper(path)
+    }
+
+    /// 为多个输出分别设置壁纸
+    fn set_wallpapers(&self, assignments: &[(String, PathBuf)]) -> Result<(), String> {
+        for (output, path) in assignments {
+            self.set_wallpaper_on(output, path)?;
+        }
+        Ok(())
+    }
+
+    /// 只更换一个输出的壁纸，others 为其他输出当前显示的壁纸
+    ///
+    /// 默认实现重新设置所有输出（mpvpaper、swaybg 无法单独替换一个输出）
+    fn set_wallpaper_only_on(
+        &self,
+        output: &str,
+        path: &Path,
+        others: &[(String, PathBuf)],
+    ) -> Result<(), String> {
+        let mut assignments = others.to_vec();
+        assignments.push((output.to_string(), path.to_path_buf()));
+        self.set_wallpapers(&assignments)
+    }
+
     /// 停止当前壁纸
     fn stop(&self) -> Result<(), String>;
 
+    /// 等待壁纸实际显示在屏幕上（最多 timeout），返回是否已确认显示
+    ///
+    /// 默认实现无法查询引擎状态，等待固定时间后返回 false
+    fn wait_until_shown(&self, paths: &[PathBuf], timeout: Duration) -> bool {
+        let _ = paths;
+        thread::sleep(timeout.min(UNCONFIRMED_WAIT));
+        false
+    }
+
+    /// 查询引擎当前是否正在显示这些文件，无法查询引擎状态时返回 None
+    fn is_showing(&self, paths: &[PathBuf]) -> Option<bool> {
+        let _ = paths;
+        None
+    }
+
     /// 检查引擎是否可用
     fn is_available(&self) -> bool;
 }
 
-/// 根据引擎类型创建对应的引擎实例
-pub fn create_engine(engine_type: &str) -> Box<dyn PaperEngine> {
+/// 所有支持的引擎类型
+pub const ENGINE_TYPES: &[&str] = &["mpvpaper", "swww", "swaybg", "wpaperd"];
+
+/// 根据引擎类型创建对应的引擎实例，引擎参数从配置中读取
+///
+/// 未知的引擎类型返回错误，不会回退到其他引擎
+pub fn create_engine(engine_type: &str, config: &Config) -> Result<Box<dyn PaperEngine>, String> {
     match engine_type {
-        "mpvpaper" => Box::new(mpvpaper::MpvPaper::new()),
-        "swww" => Box::new(swww::Swww::new()),
-        _ => {
-            eprintln!("未知引擎类型: {}, 使用默认 mpvpaper", engine_type);
-            Box::new(mpvpaper::MpvPaper::new())
+        "mpvpaper" => Ok(Box::new(create_mpvpaper(config))),
+        "swww" => Ok(Box::new(create_swww(config))),
+        "swaybg" => Ok(Box::new(swaybg::SwayBg::new())),
+        "wpaperd" => Ok(Box::new(wpaperd::Wpaperd::new())),
+        _ => Err(unknown_engine(engine_type)),
+    }
+}
+
+/// 未知引擎类型的错误信息
+fn unknown_engine(engine_type: &str) -> String {
+    format!(
+        "未知引擎类型 \"{}\"（可选值: {}）",
+        engine_type,
+        ENGINE_TYPES.join(", ")
+    )
+}
+
+/// 根据配置创建 mpvpaper 引擎，参数为空时使用默认参数
+///
+/// hwdec 为 "auto" 时按显卡类型选择硬件解码方式，mpvpaper_options 中已写明非 auto 的 --hwdec 时保留
+fn create_mpvpaper(config: &Config) -> mpvpaper::MpvPaper {
+    let mut options = config.video_engine.mpvpaper_options.trim();
+    if options.is_empty() {
+        log::warn!(
+            "mpvpaper_options 为空，使用默认参数: {}",
+            mpvpaper::DEFAULT_OPTIONS
+        );
+        options = mpvpaper::DEFAULT_OPTIONS;
+    }
+
+    let configured = config.video_engine.hwdec.trim().to_lowercase();
+    let hwdec = if !configured.is_empty() && configured != "auto" {
+        Some(configured)
+    } else if mpvpaper::option_hwdec(options).is_some_and(|v| v != "auto") {
+        None
+    } else {
+        let gpu = vram::detect_gpu_type();
+        let hwdec = mpvpaper::hwdec_for_gpu(gpu);
+        log::debug!("显卡类型 {:?}，使用 --hwdec={}", gpu, hwdec);
+        Some(hwdec.to_string())
+    };
+    let engine = match hwdec {
+        Some(hwdec) => {
+            mpvpaper::MpvPaper::with_options(&mpvpaper::set_option_hwdec(options, &hwdec))
         }
+        None => mpvpaper::MpvPaper::with_options(options),
+    };
+
+    let output = config.video_engine.mpvpaper_output.trim();
+    engine.with_output(if output.is_empty() { "*" } else { output })
+}
+
+/// 根据配置创建 swww 引擎，无效的缩放模式回退为 crop
+fn create_swww(config: &Config) -> swww::Swww {
+    let image = &config.image_engine;
+
+    let fill_mode = image.fill_mode.trim().to_lowercase();
+    let resize_mode = if swww::RESIZE_MODES.contains(&fill_mode.as_str()) {
+        fill_mode
+    } else {
+        log::warn!(
+            "无效的 fill_mode \"{}\"（可选值: {}），使用 crop",
+            image.fill_mode,
+            swww::RESIZE_MODES.join(", ")
+        );
+        "crop".to_string()
+    };
+
+    let transition_pool = image
+        .transition_pool
+        .iter()
+        .filter_map(|name| {
+            let transition = swww::TransitionType::from_str(name);
+            if transition.as_str() == name.trim().to_lowercase() {
+                Some(transition)
+            } else {
+                log::warn!("transition_pool 中的过渡效果无效，已忽略: {}", name);
+                None
+            }
+        })
+        .collect();
+
+    swww::Swww {
+        transition_pool,
+        transition_fps: image.transition_fps,
+        transition_step: image.transition_step.max(1),
+        resize_mode,
+        ..swww::Swww::with_transition(&image.transition, image.transition_duration)
     }
 }
 
-/// 获取引擎支持的文件扩展名
-pub fn supported_extensions(engine_type: &str) -> Vec<&'static str> {
+/// 获取引擎支持的文件扩展名，未知的引擎类型返回错误
+pub fn supported_extensions(engine_type: &str) -> Result<Vec<&'static str>, String> {
     match engine_type {
-        "mpvpaper" => mpvpaper::MpvPaper::supported_extensions().to_vec(),
-        "swww" => swww::Swww::supported_extensions().to_vec(),
-        _ => mpvpaper::MpvPaper::supported_extensions().to_vec(),
+        "mpvpaper" => Ok(mpvpaper::MpvPaper::supported_extensions().to_vec()),
+        "swww" => Ok(swww::Swww::supported_extensions().to_vec()),
+        "swaybg" => Ok(swaybg::SwayBg::supported_extensions().to_vec()),
+        "wpaperd" => Ok(wpaperd::Wpaperd::supported_extensions().to_vec()),
+        _ => Err(unknown_engine(engine_type)),
     }
 }
@@ -1,27 +1,67 @@
 #![allow(dead_code)]
 
 use super::PaperEngine;
-use std::path::Path;
+use crate::vram::GpuType;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// mpvpaper 默认传给 mpv 的参数（硬件解码由 video_engine.hwdec 单独设置）
+pub const DEFAULT_OPTIONS: &str = "--loop --no-audio";
+
+/// 根据显卡类型选择 mpv 的硬件解码方式，未检测到显卡时关闭硬件解码
+pub fn hwdec_for_gpu(gpu: GpuType) -> &'static str {
+    match gpu {
+        GpuType::Nvidia => "nvdec",
+        GpuType::Amd | GpuType::Intel => "vaapi",
+        GpuType::Unknown => "no",
+    }
+}
+
+/// mpv 参数中 --hwdec 的值
+pub fn option_hwdec(options: &str) -> Option<&str> {
+    options
+        .split_whitespace()
+        .find_map(|arg| arg.strip_prefix("--hwdec="))
+}
+
+/// 将 mpv 参数中的 --hwdec 替换为指定值，没有时追加
+pub fn set_option_hwdec(options: &str, hwdec: &str) -> String {
+    let flag = format!("--hwdec={}", hwdec);
+    let mut args: Vec<&str> = options
+        .split_whitespace()
+        .filter(|arg| !arg.starts_with("--hwdec="))
+        .collect();
+    args.push(&flag);
+    args.join(" ")
+}
+
 /// mpvpaper 动态壁纸引擎
 pub struct MpvPaper {
     pub options: String,
+    /// 目标输出（"*" 表示所有显示器）
+    pub output: String,
 }
 
 impl MpvPaper {
     pub fn new() -> Self {
         Self {
-            options: "--loop --no-audio --hwdec=auto".to_string(),
+            options: DEFAULT_OPTIONS.to_string(),
+            output: "*".to_string(),
         }
     }
 
     pub fn with_options(options: &str) -> Self {
         Self {
             options: options.to_string(),
+            ..Self::new()
         }
     }
 
+    pub fn with_output(mut self, output: &str) -> Self {
+        self.output = output.to_string();
+        self
+    }
+
     pub fn supported_extensions() -> &'static [&'static str] {
         &[
             "mp4", "mkv", "webm", "avi", "mov", "flv", "wmv", "m4v", "gif",
@@ -42,9 +82,19 @@ impl PaperEngine for MpvPaper {
 
     fn set_wallpaper(&self, path: &Path) -> Result<(), String> {
         self.stop()?;
+        self.set_wallpaper_on(&self.output, path)
+    }
+
+    fn set_wallpaper_on(&self, output: &str, path: &Path) -> Result<(), String> {
+        log::debug!(
+            "mpvpaper -o \"{}\" {} {}",
+            self.options,
+            output,
+            path.display()
+        );
 
         let result = Command::new("mpvpaper")
-            .args(["-o", &self.options, "*"])
+            .args(["-o", &self.options, output])
             .arg(path)
             .spawn();
 
@@ -54,6 +104,14 @@ impl PaperEngine for MpvPaper {
         }
     }
 
+    fn set_wallpapers(&self, assignments: &[(String, PathBuf)]) -> Result<(), String> {
+        self.stop()?;
+        for (output, path) in assignments {
+            self.set_wallpaper_on(output, path)?;
+        }
+        Ok(())
+    }
+
     fn stop(&self) -> Result<(), String> {
         let result = Command::new("pkill").arg("mpvpaper").status();
 
@@ -63,6 +121,20 @@ impl PaperEngine for MpvPaper {
         }
     }
 
+    fn is_showing(&self, paths: &[PathBuf]) -> Option<bool> {
+        // mpvpaper 的命令行参数中包含正在播放的文件
+        let output = Command::new("pgrep")
+            .args(["-a", "mpvpaper"])
+            .output()
+            .ok()?;
+        let running = String::from_utf8_lossy(&output.stdout);
+        Some(
+            paths
+                .iter()
+                .all(|path| running.contains(&*path.to_string_lossy())),
+        )
+    }
+
     fn is_available(&self) -> bool {
         Command::new("which")
             .arg("mpvpaper")
@@ -71,3 +143,22 @@ impl PaperEngine for MpvPaper {
             .unwrap_or(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_option_hwdec() {
+        assert_eq!(option_hwdec("--loop --hwdec=auto --no-audio"), Some("auto"));
+        assert_eq!(option_hwdec(DEFAULT_OPTIONS), None);
+        assert_eq!(
+            set_option_hwdec("--loop --hwdec=auto --no-audio", "vaapi"),
+            "--loop --no-audio --hwdec=vaapi"
+        );
+        assert_eq!(
+            set_option_hwdec(DEFAULT_OPTIONS, hwdec_for_gpu(GpuType::Nvidia)),
+            "--loop --no-audio --hwdec=nvdec"
+        );
+    }
+}
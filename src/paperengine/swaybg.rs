@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+
+use super::PaperEngine;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// swaybg 静态壁纸引擎
+pub struct SwayBg {
+    /// 缩放模式: stretch, fit, fill, center, tile, solid_color
+    pub mode: String,
+}
+
+impl SwayBg {
+    pub fn new() -> Self {
+        Self {
+            mode: "fill".to_string(),
+        }
+    }
+
+    pub fn with_mode(mode: &str) -> Self {
+        Self {
+            mode: mode.to_string(),
+        }
+    }
+
+    pub fn supported_extensions() -> &'static [&'static str] {
+        super::swww::Swww::supported_extensions()
+    }
+}
+
+impl Default for SwayBg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaperEngine for SwayBg {
+    fn name(&self) -> &'static str {
+        "swaybg"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), String> {
+        self.stop()?;
+        log::debug!("swaybg -i {} -m {}", path.display(), self.mode);
+
+        let result = Command::new("swaybg")
+            .arg("-i")
+            .arg(path)
+            .args(["-m", &self.mode])
+            .spawn();
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("启动 swaybg 失败: {}", e)),
+        }
+    }
+
+    fn set_wallpaper_on(&self, output: &str, path: &Path) -> Result<(), String> {
+        self.set_wallpapers(&[(output.to_string(), path.to_path_buf())])
+    }
+
+    fn set_wallpapers(&self, assignments: &[(String, PathBuf)]) -> Result<(), String> {
+        self.stop()?;
+
+        // 单个 swaybg 进程即可为多个输出分别设置壁纸
+        let mut command = Command::new("swaybg");
+        for (output, path) in assignments {
+            log::debug!("swaybg -o {} -i {}", output, path.display());
+            command
+                .args(["-o", output, "-i"])
+                .arg(path)
+                .args(["-m", &self.mode]);
+        }
+
+        match command.spawn() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("启动 swaybg 失败: {}", e)),
+        }
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        let result = Command::new("pkill").args(["-x", "swaybg"]).status();
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("停止 swaybg 失败: {}", e)),
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new("which")
+            .arg("swaybg")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
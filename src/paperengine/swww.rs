@@ -1,10 +1,17 @@
 #![allow(dead_code)]
 
 use super::PaperEngine;
-use std::path::Path;
-use std::process::Command;
+use rand::seq::SliceRandom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// 等待 swww-daemon 就绪的最长时间
+const DAEMON_READY_TIMEOUT: Duration = Duration::from_secs(3);
+/// 轮询 swww query 的间隔
+const DAEMON_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// swww 支持的过渡效果
 #[derive(Debug, Clone)]
@@ -66,6 +73,9 @@ impl TransitionType {
     }
 }
 
+/// swww 支持的缩放模式（--resize）
+pub const RESIZE_MODES: &[&str] = &["crop", "fit", "stretch", "no"];
+
 /// swww 静态壁纸引擎
 pub struct Swww {
     /// 过渡效果类型
@@ -78,6 +88,8 @@ pub struct Swww {
     pub transition_step: u8,
     /// 缩放模式: crop, fit, stretch, no
     pub resize_mode: String,
+    /// 过渡效果池：非空时每次切换从中随机选取，忽略 transition_type
+    pub transition_pool: Vec<TransitionType>,
 }
 
 impl Swww {
@@ -88,6 +100,7 @@ impl Swww {
             transition_fps: 60,
             transition_step: 20,
             resize_mode: "crop".to_string(),
+            transition_pool: Vec::new(),
         }
     }
 
@@ -99,6 +112,13 @@ impl Swww {
         }
     }
 
+    /// 本次切换使用的过渡效果
+    fn pick_transition(&self) -> &TransitionType {
+        self.transition_pool
+            .choose(&mut rand::thread_rng())
+            .unwrap_or(&self.transition_type)
+    }
+
     fn is_daemon_running(&self) -> bool {
         Command::new("pgrep")
             .arg("-x")
@@ -113,13 +133,48 @@ impl Swww {
             .arg("--no-cache")  // 不加载缓存，避免启动时显示旧壁纸
             .spawn();
 
-        match result {
-            Ok(_) => {
-                thread::sleep(Duration::from_millis(500));
-                Ok(())
+        if let Err(e) = result {
+            return Err(format!("启动 swww-daemon 失败: {}", e));
+        }
+
+        self.wait_daemon_ready()
+    }
+
+    /// 轮询 `swww query` 直到 daemon 可以响应，超时返回错误
+    fn wait_daemon_ready(&self) -> Result<(), String> {
+        let start = Instant::now();
+        while start.elapsed() < DAEMON_READY_TIMEOUT {
+            let ready = Command::new("swww")
+                .arg("query")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false);
+
+            if ready {
+                log::debug!("swww-daemon 已就绪（{} 毫秒）", start.elapsed().as_millis());
+                return Ok(());
             }
-            Err(e) => Err(format!("启动 swww-daemon 失败: {}", e)),
+            thread::sleep(DAEMON_POLL_INTERVAL);
         }
+
+        Err(format!("swww-daemon 在 {} 秒内未就绪", DAEMON_READY_TIMEOUT.as_secs()))
+    }
+
+    /// `swww query` 的输出中是否已包含所有路径（swww 记录的可能是规范化后的路径）
+    fn query_shows(&self, paths: &[PathBuf]) -> bool {
+        let output = match Command::new("swww").arg("query").stderr(Stdio::null()).output() {
+            Ok(output) if output.status.success() => output,
+            _ => return false,
+        };
+        let displayed = String::from_utf8_lossy(&output.stdout);
+
+        paths.iter().all(|path| {
+            let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            displayed.contains(&*path.to_string_lossy())
+                || displayed.contains(&*canonical.to_string_lossy())
+        })
     }
 
     pub fn supported_extensions() -> &'static [&'static str] {
@@ -139,6 +194,66 @@ impl PaperEngine for Swww {
     }
 
     fn set_wallpaper(&self, path: &Path) -> Result<(), String> {
+        self.set_image(path, None)
+    }
+
+    fn set_wallpaper_on(&self, output: &str, path: &Path) -> Result<(), String> {
+        self.set_image(path, Some(output))
+    }
+
+    fn set_wallpaper_only_on(
+        &self,
+        output: &str,
+        path: &Path,
+        _others: &[(String, PathBuf)],
+    ) -> Result<(), String> {
+        // swww img -o 只影响指定输出，其他输出保持原样
+        self.set_image(path, Some(output))
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        let result = Command::new("swww")
+            .arg("kill")
+            .status();
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("停止 swww 失败: {}", e)),
+        }
+    }
+
+    fn wait_until_shown(&self, paths: &[PathBuf], timeout: Duration) -> bool {
+        let start = Instant::now();
+        loop {
+            if self.query_shows(paths) {
+                log::debug!("swww 已显示新壁纸（{} 毫秒）", start.elapsed().as_millis());
+                return true;
+            }
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(DAEMON_POLL_INTERVAL);
+        }
+    }
+
+    fn is_showing(&self, paths: &[PathBuf]) -> Option<bool> {
+        Some(self.query_shows(paths))
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new("which")
+            .arg("swww")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
+
+impl Swww {
+    /// 执行 swww img，output 为 None 时应用到所有输出
+    fn set_image(&self, path: &Path, output: Option<&str>) -> Result<(), String> {
+        let outputs: Vec<&str> = output.map(|o| vec!["-o", o]).unwrap_or_default();
+        log::debug!("swww img {} (输出: {})", path.display(), output.unwrap_or("全部"));
         let daemon_was_running = self.is_daemon_running();
         
         if !daemon_was_running {
@@ -149,6 +264,7 @@ impl PaperEngine for Swww {
             let result = Command::new("swww")
                 .arg("img")
                 .arg(path)
+                .args(&outputs)
                 .args([
                     "--transition-type", "none",
                     "--resize", &self.resize_mode,
@@ -166,8 +282,9 @@ impl PaperEngine for Swww {
         let result = Command::new("swww")
             .arg("img")
             .arg(path)
+            .args(&outputs)
             .args([
-                "--transition-type", self.transition_type.as_str(),
+                "--transition-type", self.pick_transition().as_str(),
                 "--transition-duration", &self.transition_duration.to_string(),
                 "--transition-fps", &self.transition_fps.to_string(),
                 "--transition-step", &self.transition_step.to_string(),
@@ -181,23 +298,4 @@ impl PaperEngine for Swww {
             Err(e) => Err(format!("执行 swww 失败: {}", e)),
         }
     }
-
-    fn stop(&self) -> Result<(), String> {
-        let result = Command::new("swww")
-            .arg("kill")
-            .status();
-
-        match result {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("停止 swww 失败: {}", e)),
-        }
-    }
-
-    fn is_available(&self) -> bool {
-        Command::new("which")
-            .arg("swww")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
-    }
 }
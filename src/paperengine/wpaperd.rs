@@ -0,0 +1,228 @@
+#![allow(dead_code)]
+
+use super::PaperEngine;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use toml_edit::{DocumentMut, Item};
+
+/// 所有显示器共用的段（没有单独配置的显示器使用此段）
+const ANY_SECTION: &str = "any";
+/// wpaperd 的默认参数段，不对应显示器，不写入壁纸路径
+const DEFAULT_SECTION: &str = "default";
+
+/// wpaperd 静态壁纸引擎
+///
+/// wpaperd 没有直接设置图片的命令，而是读取自己的配置文件并在文件变化时自动重新加载，
+/// 因此通过改写配置文件中各显示器段的 path 切换壁纸，其他配置项保持不变
+pub struct Wpaperd {
+    /// wpaperd 配置文件路径
+    pub config_path: PathBuf,
+}
+
+impl Wpaperd {
+    pub fn new() -> Self {
+        Self {
+            config_path: default_config_path(),
+        }
+    }
+
+    pub fn supported_extensions() -> &'static [&'static str] {
+        super::swww::Swww::supported_extensions()
+    }
+
+    fn is_daemon_running(&self) -> bool {
+        Command::new("pgrep")
+            .args(["-x", "wpaperd"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    /// 改写配置文件中的壁纸路径，wpaperd 未运行时启动它
+    fn apply(&self, all: Option<&Path>, outputs: &[(String, PathBuf)]) -> Result<(), String> {
+        let path = &self.config_path;
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("无法读取 {}: {}", path.display(), e)),
+        };
+        let updated = update_config(&content, all, outputs)
+            .map_err(|e| format!("无法解析 {}: {}", path.display(), e))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("无法创建 {}: {}", parent.display(), e))?;
+        }
+        // 先写临时文件再重命名，避免 wpaperd 读到写了一半的配置
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, updated)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| {
+                fs::remove_file(&tmp).ok();
+                format!("无法写入 {}: {}", path.display(), e)
+            })?;
+        log::debug!("已更新 wpaperd 配置: {}", path.display());
+
+        if self.is_daemon_running() {
+            return Ok(());
+        }
+        Command::new("wpaperd")
+            .arg("-d")
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| format!("启动 wpaperd 失败: {}", e))
+    }
+}
+
+impl Default for Wpaperd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PaperEngine for Wpaperd {
+    fn name(&self) -> &'static str {
+        "wpaperd"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> Result<(), String> {
+        self.apply(Some(path), &[])
+    }
+
+    fn set_wallpaper_on(&self, output: &str, path: &Path) -> Result<(), String> {
+        self.apply(None, &[(output.to_string(), path.to_path_buf())])
+    }
+
+    fn set_wallpapers(&self, assignments: &[(String, PathBuf)]) -> Result<(), String> {
+        self.apply(None, assignments)
+    }
+
+    /// 每个显示器是配置文件中独立的段，只改写这一段即可
+    fn set_wallpaper_only_on(
+        &self,
+        output: &str,
+        path: &Path,
+        _others: &[(String, PathBuf)],
+    ) -> Result<(), String> {
+        self.set_wallpaper_on(output, path)
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        let result = Command::new("pkill").args(["-x", "wpaperd"]).status();
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("停止 wpaperd 失败: {}", e)),
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new("which")
+            .arg("wpaperd")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// wpaperd 配置文件路径（$XDG_CONFIG_HOME/wpaperd/config.toml）
+fn default_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("~/.config"))
+        .join("wpaperd/config.toml")
+}
+
+/// 在 wpaperd 配置中设置壁纸路径，返回新的配置内容
+///
+/// all 设置 [any] 段并覆盖所有已有显示器段的 path；outputs 只设置对应显示器的段。
+/// 使用 toml_edit 原样保留其他配置项、注释和格式
+fn update_config(
+    content: &str,
+    all: Option<&Path>,
+    outputs: &[(String, PathBuf)],
+) -> Result<String, String> {
+    let mut config: DocumentMut = content.parse().map_err(|e| format!("{}", e))?;
+    let sections: Vec<String> = config.iter().map(|(key, _)| key.to_string()).collect();
+
+    let mut set_path = |section: &str, path: &Path| {
+        let entry = config.entry(section).or_insert_with(toml_edit::table);
+        let Some(table) = entry.as_table_like_mut() else {
+            return;
+        };
+        let path = path.to_string_lossy();
+        // 原地替换已有的值，保留键前的注释和行尾注释
+        if let Some(value) = table.get_mut("path").and_then(Item::as_value_mut) {
+            let decor = value.decor().clone();
+            *value = path.as_ref().into();
+            *value.decor_mut() = decor;
+        } else {
+            table.insert("path", toml_edit::value(path.as_ref()));
+        }
+    };
+
+    if let Some(path) = all {
+        set_path(ANY_SECTION, path);
+        for section in sections.iter().filter(|s| s.as_str() != DEFAULT_SECTION) {
+            set_path(section, path);
+        }
+    }
+    for (output, path) in outputs {
+        set_path(output, path);
+    }
+
+    Ok(config.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_config_keeps_other_settings() {
+        let content = r#"
+[default]
+duration = "30m"
+mode = "center"
+
+[DP-1]
+path = "/old/a.png"
+"#;
+        let updated = update_config(
+            content,
+            None,
+            &[("HDMI-A-1".to_string(), PathBuf::from("/new/b.png"))],
+        )
+        .unwrap();
+        let config: toml::Table = updated.parse().unwrap();
+        assert_eq!(config["default"]["duration"].as_str(), Some("30m"));
+        assert_eq!(config["DP-1"]["path"].as_str(), Some("/old/a.png"));
+        assert_eq!(config["HDMI-A-1"]["path"].as_str(), Some("/new/b.png"));
+
+        // 设置所有显示器时覆盖已有显示器段，[default] 不写入路径
+        let updated = update_config(&updated, Some(Path::new("/new/c.png")), &[]).unwrap();
+        let config: toml::Table = updated.parse().unwrap();
+        for section in ["any", "DP-1", "HDMI-A-1"] {
+            assert_eq!(config[section]["path"].as_str(), Some("/new/c.png"));
+        }
+        assert!(config["default"].get("path").is_none());
+
+        assert!(update_config("[DP-1", None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_update_config_keeps_comments() {
+        let content = r#"# 由用户维护的配置
+[default]
+duration = "30m" # 每 30 分钟切换
+
+[DP-1]
+# 主显示器
+path = "/old/a.png" # 旧壁纸
+"#;
+        let updated = update_config(content, Some(Path::new("/new/c.png")), &[]).unwrap();
+        assert!(updated.starts_with("# 由用户维护的配置\n[default]\n"));
+        assert!(updated.contains("duration = \"30m\" # 每 30 分钟切换"));
+        assert!(updated.contains("# 主显示器\npath = \"/new/c.png\" # 旧壁纸\n"));
+    }
+}
@@ -0,0 +1,90 @@
+/// systemd 通知模块
+///
+/// 启用 `systemd` feature 时通过 sd_notify 向 systemd 报告守护进程状态，
+/// 用于 `Type=notify` 服务；未启用时所有函数为空操作
+use std::time::{Duration, Instant};
+
+/// systemd 看门狗
+///
+/// 服务配置了 `WatchdogSec=` 时，systemd 会设置 WATCHDOG_USEC，
+/// 守护进程需要在该时间内定期发送 WATCHDOG=1
+pub struct Watchdog {
+    interval: Option<Duration>,
+    last_ping: Instant,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        // 以超时时间的一半作为发送间隔，留出余量
+        let interval = watchdog_timeout().map(|timeout| timeout / 2);
+        if let Some(interval) = interval {
+            log::info!(
+                "systemd 看门狗: 每 {:.1} 秒发送一次",
+                interval.as_secs_f64()
+            );
+        }
+        Self {
+            interval,
+            last_ping: Instant::now(),
+        }
+    }
+
+    /// 到达发送间隔时发送 WATCHDOG=1
+    pub fn ping(&mut self) {
+        if let Some(interval) = self.interval
+            && self.last_ping.elapsed() >= interval
+        {
+            self.last_ping = Instant::now();
+            notify_watchdog();
+        }
+    }
+}
+
+#[cfg(feature = "systemd")]
+mod imp {
+    use sd_notify::NotifyState;
+    use std::time::Duration;
+
+    fn notify(state: NotifyState) {
+        if let Err(e) = sd_notify::notify(&[state]) {
+            log::debug!("sd_notify 失败: {}", e);
+        }
+    }
+
+    pub fn notify_ready() {
+        notify(NotifyState::Ready);
+    }
+
+    pub fn notify_stopping() {
+        notify(NotifyState::Stopping);
+    }
+
+    pub fn notify_watchdog() {
+        notify(NotifyState::Watchdog);
+    }
+
+    pub fn watchdog_timeout() -> Option<Duration> {
+        sd_notify::watchdog_enabled()
+    }
+}
+
+#[cfg(not(feature = "systemd"))]
+mod imp {
+    use std::time::Duration;
+
+    pub fn notify_ready() {}
+
+    pub fn notify_stopping() {}
+
+    pub fn notify_watchdog() {}
+
+    pub fn watchdog_timeout() -> Option<Duration> {
+        None
+    }
+}
+
+/// 通知 systemd 守护进程已就绪（READY=1）
+pub use imp::notify_ready;
+/// 通知 systemd 守护进程正在退出（STOPPING=1）
+pub use imp::notify_stopping;
+use imp::{notify_watchdog, watchdog_timeout};
@@ -1,11 +1,17 @@
 /// 显存监控模块
 ///
 /// 当前仅强支持 NVIDIA 显卡（通过 nvidia-smi）
-/// AMD 显卡基本支持（通过 rocm-smi），Intel 显卡暂不支持
+/// AMD 显卡基本支持（优先读取 sysfs，其次 rocm-smi）
+/// Intel 显卡通过 sysfs 支持（需要驱动提供 mem_info_vram_* 文件）
 ///
 /// TODO: 未来可考虑添加更多显卡支持
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// DRM 设备所在的 sysfs 目录
+const DRM_SYSFS_DIR: &str = "/sys/class/drm";
+
 /// 显存使用信息
 #[derive(Debug, Clone)]
 pub struct VramInfo {
@@ -25,7 +31,7 @@ pub struct VramInfo {
 pub enum GpuType {
     Nvidia,
     Amd,
-    Intel, // 暂不支持，预留
+    Intel,
     Unknown,
 }
 
@@ -41,6 +47,18 @@ pub fn detect_gpu_type() -> GpuType {
         return GpuType::Nvidia;
     }
 
+    // 通过 sysfs 的 PCI 厂商 ID 检测 AMD / Intel
+    for device in drm_devices() {
+        match fs::read_to_string(device.join("vendor"))
+            .as_deref()
+            .map(str::trim)
+        {
+            Ok("0x1002") => return GpuType::Amd,
+            Ok("0x8086") => return GpuType::Intel,
+            _ => {}
+        }
+    }
+
     // 检测 AMD (ROCm)
     if Command::new("which")
         .arg("rocm-smi")
@@ -51,19 +69,80 @@ pub fn detect_gpu_type() -> GpuType {
         return GpuType::Amd;
     }
 
-    // Intel 暂不支持
     GpuType::Unknown
 }
 
+/// 列出 /sys/class/drm 下所有显卡的 device 目录（card0、card1 ...，不含 card0-DP-1 等接口）
+fn drm_devices() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(DRM_SYSFS_DIR) else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.strip_prefix("card")
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|e| e.path().join("device"))
+        .collect();
+    devices.sort();
+    devices
+}
+
 /// 获取显存使用信息
 ///
 /// 返回 None 表示无法获取（不支持的显卡或命令失败）
 pub fn get_vram_info() -> Option<VramInfo> {
     match detect_gpu_type() {
         GpuType::Nvidia => get_nvidia_vram(),
-        GpuType::Amd => get_amd_vram(),
-        _ => None,
+        // sysfs 可用时优先读取，避免调用外部命令
+        GpuType::Amd => get_sysfs_vram().or_else(get_amd_vram),
+        GpuType::Intel => get_intel_vram(),
+        GpuType::Unknown => None,
+    }
+}
+
+/// Intel 显卡：通过 sysfs 获取显存信息
+fn get_intel_vram() -> Option<VramInfo> {
+    get_sysfs_vram()
+}
+
+/// 通过 sysfs 获取显存信息
+///
+/// 读取 /sys/class/drm/card*/device/mem_info_vram_{used,total}（单位：字节），
+/// 适用于 Intel Arc 以及较新的 AMD 显卡
+fn get_sysfs_vram() -> Option<VramInfo> {
+    drm_devices()
+        .iter()
+        .find_map(|device| read_sysfs_vram(device))
+}
+
+/// 读取单个设备目录下的显存信息
+fn read_sysfs_vram(device: &Path) -> Option<VramInfo> {
+    let used = fs::read_to_string(device.join("mem_info_vram_used")).ok()?;
+    let total = fs::read_to_string(device.join("mem_info_vram_total")).ok()?;
+    parse_sysfs_vram(&used, &total)
+}
+
+/// 解析 sysfs 中以字节为单位的显存数值
+fn parse_sysfs_vram(used: &str, total: &str) -> Option<VramInfo> {
+    let used_bytes: u64 = used.trim().parse().ok()?;
+    let total_bytes: u64 = total.trim().parse().ok()?;
+
+    if total_bytes == 0 {
+        return None;
     }
+
+    let usage_percent = (used_bytes as f64 / total_bytes as f64 * 100.0) as f32;
+
+    Some(VramInfo {
+        used_mb: used_bytes / 1024 / 1024,
+        total_mb: total_bytes / 1024 / 1024,
+        usage_percent,
+        free_percent: 100.0 - usage_percent,
+    })
 }
 
 /// NVIDIA 显卡：通过 nvidia-smi 获取显存信息
@@ -133,10 +212,10 @@ fn get_amd_vram() -> Option<VramInfo> {
             if let Some(num) = extract_mb_value(line) {
                 used_mb = Some(num);
             }
-        } else if line_lower.contains("total") {
-            if let Some(num) = extract_mb_value(line) {
-                total_mb = Some(num);
-            }
+        } else if line_lower.contains("total")
+            && let Some(num) = extract_mb_value(line)
+        {
+            total_mb = Some(num);
         }
     }
 
@@ -215,4 +294,28 @@ mod tests {
             println!("无法获取显存信息（可能没有支持的 GPU）");
         }
     }
+
+    #[test]
+    fn test_parse_sysfs_vram() {
+        // 2 GiB / 8 GiB
+        let info = parse_sysfs_vram("2147483648\n", "8589934592\n").unwrap();
+        assert_eq!(info.used_mb, 2048);
+        assert_eq!(info.total_mb, 8192);
+        assert!((info.usage_percent - 25.0).abs() < 0.01);
+        assert!((info.free_percent - 75.0).abs() < 0.01);
+
+        assert!(parse_sysfs_vram("0", "0").is_none());
+        assert!(parse_sysfs_vram("abc", "1024").is_none());
+    }
+
+    #[test]
+    fn test_read_sysfs_vram() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("mem_info_vram_used"), "1073741824").unwrap();
+        fs::write(dir.path().join("mem_info_vram_total"), "4294967296").unwrap();
+
+        let info = read_sysfs_vram(dir.path()).unwrap();
+        assert_eq!(info.used_mb, 1024);
+        assert_eq!(info.total_mb, 4096);
+    }
 }
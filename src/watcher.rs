@@ -0,0 +1,85 @@
+/// 壁纸目录监听模块
+///
+/// 使用 notify 监听壁纸目录的新增、删除和修改事件，
+/// 事件停止一段时间后（防抖）才通知调用方重新扫描
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
+
+/// 防抖时长：最后一次事件之后等待多久再触发重新扫描
+///
+/// 复制大文件时会连续产生大量修改事件，等待写入结束后再扫描
+const DEBOUNCE: Duration = Duration::from_secs(3);
+
+/// 目录监听器
+pub struct DirWatcher {
+    /// 需要持有 watcher，drop 后监听会停止
+    _watcher: RecommendedWatcher,
+    rx: Receiver<notify::Result<Event>>,
+    /// 最近一次相关事件的时间，None 表示没有待处理的变化
+    last_event: Option<Instant>,
+}
+
+impl DirWatcher {
+    /// 递归监听指定目录，不存在的目录会被跳过
+    ///
+    /// 所有目录都无法监听时返回 None
+    pub fn new(dirs: &[PathBuf]) -> Option<Self> {
+        let (tx, rx) = channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(w) => w,
+            Err(e) => {
+                log::warn!("无法创建目录监听器: {}", e);
+                return None;
+            }
+        };
+
+        let mut watching = 0;
+        for dir in dirs.iter().filter(|d| d.is_dir()) {
+            match watcher.watch(dir, RecursiveMode::Recursive) {
+                Ok(()) => {
+                    log::info!("监听目录: {}", dir.display());
+                    watching += 1;
+                }
+                Err(e) => log::warn!("无法监听目录 {}: {}", dir.display(), e),
+            }
+        }
+
+        (watching > 0).then_some(Self {
+            _watcher: watcher,
+            rx,
+            last_event: None,
+        })
+    }
+
+    /// 处理已收到的事件，目录发生变化且已过防抖时间时返回 true
+    pub fn poll(&mut self) -> bool {
+        for event in self.rx.try_iter() {
+            match event {
+                Ok(event) if is_relevant(&event.kind) => {
+                    log::debug!("目录变化: {:?} {:?}", event.kind, event.paths);
+                    self.last_event = Some(Instant::now());
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("目录监听出错: {}", e),
+            }
+        }
+
+        match self.last_event {
+            Some(last) if last.elapsed() >= DEBOUNCE => {
+                self.last_event = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 只关心会影响壁纸列表的事件（忽略访问等事件）
+fn is_relevant(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_)
+    )
+}